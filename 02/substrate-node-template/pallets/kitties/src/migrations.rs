@@ -0,0 +1,41 @@
+//! Storage migrations for pallet-kitties.
+
+/// Version 1: `Kitties`, `Owner` and `KittiesCount` drop the redundant `Option` wrapper
+/// that was stored under `ValueQuery`, so every entry loses its leading option byte.
+pub mod v1 {
+    use crate::{Config, Kitties, KittiesCount, Kitty, Owner, Pallet};
+    use frame_support::{
+        traits::{Get, GetStorageVersion, StorageVersion},
+        weights::Weight,
+    };
+
+    /// Re-encode every existing entry and bump the storage version to 1.
+    ///
+    /// Does nothing if the on-chain storage version is already 1 or higher.
+    pub fn migrate<T: Config>() -> Weight {
+        let on_chain = Pallet::<T>::on_chain_storage_version();
+        if on_chain >= 1 {
+            return 0;
+        }
+
+        let mut translated: u64 = 0;
+
+        // Entries explicitly stored as `None` carried no kitty, so they are dropped.
+        Kitties::<T>::translate::<Option<Kitty>, _>(|_, old| {
+            translated += 1;
+            old
+        });
+        Owner::<T>::translate::<Option<T::AccountId>, _>(|_, old| {
+            translated += 1;
+            old
+        });
+        let _ = KittiesCount::<T>::translate::<Option<u64>, _>(|old| {
+            translated += 1;
+            old.flatten()
+        });
+
+        StorageVersion::new(1).put::<Pallet<T>>();
+
+        T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+    }
+}
@@ -1,10 +1,11 @@
 use crate as pallet_kitties;
-use frame_support::parameter_types;
+use frame_support::{parameter_types, PalletId};
 use frame_system as system;
 use sp_core::H256;
 use sp_runtime::{
     testing::Header,
-    traits::{BlakeTwo256, IdentityLookup},
+    traits::{AccountIdConversion, BlakeTwo256, IdentityLookup},
+    Permill,
 };
 parameter_types! {
     pub const ExistentialDeposit: u128 = 500;
@@ -58,9 +59,25 @@ impl system::Config for Test {
     type OnSetCode = ();
 }
 
+parameter_types! {
+    pub const KittiesPalletId: PalletId = PalletId(*b"py/kitty");
+    pub const AdventureRewardPerBlock: Balance = 10;
+    pub const MaxAdventureDuration: u64 = 100;
+    pub const MarketFee: Permill = Permill::from_percent(5);
+    pub const BreederRoyalty: Permill = Permill::from_percent(10);
+    pub KittiesTreasury: u64 = KittiesPalletId::get().into_account();
+}
+
 impl pallet_kitties::Config for Test {
     type Event = Event;
     type Randomness = RandomnessCollectiveFlip;
+    type PalletId = KittiesPalletId;
+    type AdventureRewardPerBlock = AdventureRewardPerBlock;
+    type MaxAdventureDuration = MaxAdventureDuration;
+    type MarketFee = MarketFee;
+    type BreederRoyalty = BreederRoyalty;
+    type Treasury = KittiesTreasury;
+    type WeightInfo = ();
 }
 
 impl pallet_randomness_collective_flip::Config for Test {}
@@ -49,10 +49,8 @@ export default function Kitties (props) {
 
       // console.log(d)
       //let kittyIndex
-      if (d.isSome) {
-        kittyIndex = d.unwrap().toNumber()
-        // console.log(kittyIndex)
-      }
+      kittyIndex = d.toNumber()
+      // console.log(kittyIndex)
       
       //console.log('kittyIndex: ' + kittyIndex)
       setkittyIndex(kittyIndex)
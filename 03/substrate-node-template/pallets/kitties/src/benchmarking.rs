@@ -1,20 +1,120 @@
-//! Benchmarking setup for pallet-template
+//! Benchmarking setup for pallet-kitties
 
 use super::*;
 
 #[allow(unused)]
-use crate::Pallet as Template;
-use frame_benchmarking::{benchmarks, impl_benchmark_test_suite, whitelisted_caller};
+use crate::Pallet as KittiesModule;
+use frame_benchmarking::{account, benchmarks, impl_benchmark_test_suite, whitelisted_caller};
+use frame_support::traits::{Currency, Get};
 use frame_system::RawOrigin;
+use sp_runtime::traits::{Bounded, Saturating};
+
+const SEED: u32 = 0;
+
+fn funded_account<T: Config>(name: &'static str, index: u32) -> T::AccountId {
+    let who: T::AccountId = account(name, index, SEED);
+    fund::<T>(&who);
+    who
+}
+
+fn fund<T: Config>(who: &T::AccountId) {
+    <pallet_balances::Pallet<T> as Currency<_>>::make_free_balance_be(
+        who,
+        T::Balance::max_value() / 16u32.into(),
+    );
+}
+
+fn create_kitty_for<T: Config>(owner: &T::AccountId) -> Result<KittyIndex, &'static str> {
+    let kitty_id = KittiesCount::<T>::get();
+    KittiesModule::<T>::create_kitty(RawOrigin::Signed(owner.clone()).into())?;
+    Ok(kitty_id)
+}
 
 benchmarks! {
-    do_something {
-        let s in 0 .. 100;
+    create_kitty {
+        let caller: T::AccountId = whitelisted_caller();
+        let kitty_id = KittiesCount::<T>::get();
+    }: _(RawOrigin::Signed(caller.clone()))
+    verify {
+        assert_eq!(Owner::<T>::get(kitty_id), Some(caller));
+    }
+
+    transfer_kitty {
+        let caller: T::AccountId = whitelisted_caller();
+        let kitty_id = create_kitty_for::<T>(&caller)?;
+        let dest: T::AccountId = account("dest", 0, SEED);
+    }: _(RawOrigin::Signed(caller), dest.clone(), kitty_id)
+    verify {
+        assert_eq!(Owner::<T>::get(kitty_id), Some(dest));
+    }
+
+    breed_kitty {
         let caller: T::AccountId = whitelisted_caller();
-    }: _(RawOrigin::Signed(caller), s)
+        let kitty1 = create_kitty_for::<T>(&caller)?;
+        let kitty2 = create_kitty_for::<T>(&caller)?;
+        let child = KittiesCount::<T>::get();
+    }: _(RawOrigin::Signed(caller.clone()), kitty1, kitty2)
+    verify {
+        assert_eq!(Breeders::<T>::get(child), Some(caller));
+    }
+
+    // Worst case: the kitty was bred by someone other than the seller, so the
+    // fee, the royalty and the seller share are all paid.
+    buy_kitty {
+        let breeder = funded_account::<T>("breeder", 0);
+        let kitty1 = create_kitty_for::<T>(&breeder)?;
+        let kitty2 = create_kitty_for::<T>(&breeder)?;
+        let kitty_id = KittiesCount::<T>::get();
+        KittiesModule::<T>::breed_kitty(RawOrigin::Signed(breeder.clone()).into(), kitty1, kitty2)?;
+        let seller = funded_account::<T>("seller", 0);
+        KittiesModule::<T>::transfer_kitty(
+            RawOrigin::Signed(breeder).into(),
+            seller.clone(),
+            kitty_id,
+        )?;
+        fund::<T>(&T::Treasury::get());
+        let price = T::ExistentialDeposit::get().saturating_mul(1_000u32.into());
+        KittiesModule::<T>::sell_kitty(RawOrigin::Signed(seller).into(), kitty_id, Some(price))?;
+        let buyer = funded_account::<T>("buyer", 0);
+    }: _(RawOrigin::Signed(buyer.clone()), kitty_id, price)
+    verify {
+        assert_eq!(Owner::<T>::get(kitty_id), Some(buyer));
+    }
+
+    sell_kitty {
+        let caller: T::AccountId = whitelisted_caller();
+        let kitty_id = create_kitty_for::<T>(&caller)?;
+        let price = T::ExistentialDeposit::get();
+    }: _(RawOrigin::Signed(caller), kitty_id, Some(price))
+    verify {
+        assert_eq!(KittyPrices::<T>::get(kitty_id), Some(price));
+    }
+
+    send_on_adventure {
+        let caller: T::AccountId = whitelisted_caller();
+        let kitty_id = create_kitty_for::<T>(&caller)?;
+    }: _(RawOrigin::Signed(caller), kitty_id, T::MaxAdventureDuration::get())
+    verify {
+        assert!(Adventures::<T>::contains_key(kitty_id));
+    }
+
+    // Worst case: the pot can cover the reward, so a transfer happens.
+    claim_adventure {
+        let caller = funded_account::<T>("caller", 0);
+        let kitty_id = create_kitty_for::<T>(&caller)?;
+        fund::<T>(&KittiesModule::<T>::account_id());
+        let duration = T::MaxAdventureDuration::get();
+        KittiesModule::<T>::send_on_adventure(
+            RawOrigin::Signed(caller.clone()).into(),
+            kitty_id,
+            duration,
+        )?;
+        let now = frame_system::Pallet::<T>::block_number();
+        frame_system::Pallet::<T>::set_block_number(now.saturating_add(duration));
+    }: _(RawOrigin::Signed(caller), kitty_id)
     verify {
-        assert_eq!(Something::<T>::get(), Some(s));
+        assert!(!Adventures::<T>::contains_key(kitty_id));
     }
 }
 
-impl_benchmark_test_suite!(Template, crate::mock::new_test_ext(), crate::mock::Test,);
+impl_benchmark_test_suite!(KittiesModule, crate::mock::new_test_ext(), crate::mock::Test,);
@@ -11,26 +11,36 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+#[cfg(test)]
+mod proptests;
+
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 
+pub mod migrations;
+pub mod weights;
+
 #[frame_support::pallet]
 pub mod pallet {
     use frame_support::dispatch::fmt::Debug;
     use frame_support::{
         dispatch::DispatchResult, pallet_prelude::*, sp_runtime::traits::Hash,
         traits::tokens::currency::Currency, traits::tokens::ExistenceRequirement,
-        traits::Randomness,
+        traits::Randomness, traits::StorageVersion, transactional, PalletId,
     };
 
     use frame_support::debug;
     use frame_system::pallet_prelude::*;
+    pub use crate::weights::WeightInfo;
     use sp_core::H256;
     use sp_io::hashing::{blake2_128, blake2_256, twox_128, twox_256, twox_64};
     use sp_runtime::print;
+    use sp_runtime::traits::AccountIdConversion;
     use sp_runtime::traits::AtLeast32Bit;
     use sp_runtime::traits::MaybeDisplay;
+    use sp_runtime::traits::{CheckedSub, SaturatedConversion, Zero};
     use sp_runtime::DispatchErrorWithPostInfo;
+    use sp_runtime::{PerThing, Percent, Permill};
 
     /// Configure the pallet by specifying the parameters and types on which it depends.
     // #[pallet::config]
@@ -43,6 +53,25 @@ pub mod pallet {
     pub trait Config: pallet_balances::Config + frame_system::Config {
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
         type Randomness: Randomness<H256, Self::BlockNumber>;
+        /// The pallet id, used for deriving the account that holds the adventure reward pot.
+        #[pallet::constant]
+        type PalletId: Get<PalletId>;
+        /// Reward paid per block of adventure to a kitty with the maximum stat.
+        #[pallet::constant]
+        type AdventureRewardPerBlock: Get<Self::Balance>;
+        /// The longest adventure, in blocks, a kitty can be sent on.
+        #[pallet::constant]
+        type MaxAdventureDuration: Get<Self::BlockNumber>;
+        /// Share of every sale price paid to `Treasury`.
+        #[pallet::constant]
+        type MarketFee: Get<Permill>;
+        /// Share of every sale price paid to the account that bred the kitty.
+        #[pallet::constant]
+        type BreederRoyalty: Get<Permill>;
+        /// The account receiving market fees.
+        type Treasury: Get<Self::AccountId>;
+        /// Information on runtime weights.
+        type WeightInfo: WeightInfo;
         // type KittyIndex: Parameter
         // + Member
         // + MaybeSerializeDeserialize
@@ -54,13 +83,17 @@ pub mod pallet {
         // + Copy;
     }
 
+    /// The current storage version.
+    const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
     #[pallet::storage]
     #[pallet::getter(fn kitties_count)]
-    pub(super) type KittiesCount<T: Config> = StorageValue<_, Option<u64>, ValueQuery>;
+    pub(super) type KittiesCount<T: Config> = StorageValue<_, u64, ValueQuery>;
 
     #[pallet::storage]
     #[pallet::getter(fn get_nonce)]
@@ -69,12 +102,27 @@ pub mod pallet {
     #[pallet::storage]
     #[pallet::getter(fn kitties)]
     pub(super) type Kitties<T: Config> =
-        StorageMap<_, Blake2_128Concat, KittyIndex, Option<Kitty>, ValueQuery>;
+        StorageMap<_, Blake2_128Concat, KittyIndex, Kitty, OptionQuery>;
 
     #[pallet::storage]
     #[pallet::getter(fn owner)]
     pub(super) type Owner<T: Config> =
-        StorageMap<_, Blake2_128Concat, KittyIndex, Option<T::AccountId>, ValueQuery>;
+        StorageMap<_, Blake2_128Concat, KittyIndex, T::AccountId, OptionQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn adventures)]
+    pub(super) type Adventures<T: Config> =
+        StorageMap<_, Blake2_128Concat, KittyIndex, Adventure<T::BlockNumber>, OptionQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn kitty_prices)]
+    pub(super) type KittyPrices<T: Config> =
+        StorageMap<_, Blake2_128Concat, KittyIndex, T::Balance, OptionQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn breeders)]
+    pub(super) type Breeders<T: Config> =
+        StorageMap<_, Blake2_128Concat, KittyIndex, T::AccountId, OptionQuery>;
 
     // Pallets use events to inform users when important changes are made.
     // https://substrate.dev/docs/en/knowledgebase/runtime/events
@@ -83,10 +131,33 @@ pub mod pallet {
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
         KittyCreated(T::AccountId, u64),
-        PriceSet(T::AccountId, T::Hash, T::Balance),
+        /// A kitty was listed for sale, or delisted with `None`. \[owner, kitty_id, price\]
+        PriceSet(T::AccountId, KittyIndex, Option<T::Balance>),
         KittyTransferred(T::AccountId, T::AccountId, KittyIndex),
-        Bought(T::AccountId, T::AccountId, T::Hash, T::Balance),
+        /// A kitty was sold. \[buyer, seller, kitty_id, price\]
+        Bought(T::AccountId, T::AccountId, KittyIndex, T::Balance),
+        /// A breeder received a royalty from a sale. \[breeder, kitty_id, royalty\]
+        RoyaltyPaid(T::AccountId, KittyIndex, T::Balance),
         KittyBreeded(T::AccountId, KittyIndex, KittyIndex),
+        /// A kitty left on an adventure. \[owner, kitty_id, ends_at\]
+        AdventureStarted(T::AccountId, KittyIndex, T::BlockNumber),
+        /// A kitty came back from an adventure. \[owner, kitty_id, reward\]
+        AdventureClaimed(T::AccountId, KittyIndex, T::Balance),
+    }
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_runtime_upgrade() -> Weight {
+            crate::migrations::v1::migrate::<T>()
+        }
+
+        fn integrity_test() {
+            assert!(
+                T::MarketFee::get().deconstruct() + T::BreederRoyalty::get().deconstruct()
+                    <= Permill::one().deconstruct(),
+                "MarketFee and BreederRoyalty must add up to at most 100%",
+            );
+        }
     }
 
     // Errors inform users that something went wrong.
@@ -100,25 +171,45 @@ pub mod pallet {
         KittyIndexInvalid,
         SameParentIndex,
         KittyNotExist,
+        /// The kitty is away on an adventure and cannot be used.
+        KittyOnAdventure,
+        /// The kitty is not on an adventure.
+        KittyNotOnAdventure,
+        /// The adventure has not reached its end block yet.
+        AdventureNotFinished,
+        /// The adventure duration is zero or above `MaxAdventureDuration`.
+        InvalidAdventureDuration,
+        /// The kitty is not listed for sale.
+        KittyNotForSale,
+        /// The listed price is above the price the buyer is willing to pay.
+        KittyPriceTooHigh,
+        /// The market fee and breeder royalty add up to more than the price.
+        FeesExceedPrice,
     }
 
     // Dispatchable functions allows users to interact with the pallet and invoke state changes.
     // These functions materialize as "extrinsics", which are often compared to transactions.
     // Dispatchable functions must be annotated with a weight and must return a DispatchResult.
 
-    #[derive(Clone, Encode, Decode, Default, PartialEq)]
+    #[derive(Clone, Encode, Decode, Default, PartialEq, RuntimeDebug)]
     pub struct Kitty(pub [u8; 16]);
 
+    #[derive(Clone, Encode, Decode, Default, PartialEq, RuntimeDebug)]
+    pub struct Adventure<BlockNumber> {
+        pub started_at: BlockNumber,
+        pub duration: BlockNumber,
+    }
+
     #[pallet::call]
     impl<T: Config> Pallet<T> {
-        #[pallet::weight(10)]
+        #[pallet::weight(T::WeightInfo::create_kitty())]
         pub fn create_kitty(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
             let sender = ensure_signed(origin)?;
             let dna = Self::random_hash(&sender);
             Self::create(sender, dna)
         }
 
-        #[pallet::weight(10)]
+        #[pallet::weight(T::WeightInfo::transfer_kitty())]
         pub fn transfer_kitty(
             origin: OriginFor<T>,
             dest: T::AccountId,
@@ -133,12 +224,14 @@ pub mod pallet {
                 Some(sender.clone()) == Owner::<T>::get(kitty_id),
                 Error::<T>::NotKittyOwner
             );
-            Owner::<T>::insert(kitty_id, Some(dest.clone()));
+            Self::ensure_at_home(kitty_id)?;
+            Owner::<T>::insert(kitty_id, dest.clone());
+            KittyPrices::<T>::remove(kitty_id);
             Self::deposit_event(Event::KittyTransferred(sender, dest, kitty_id));
             Ok(().into())
         }
 
-        #[pallet::weight(10)]
+        #[pallet::weight(T::WeightInfo::breed_kitty())]
         pub fn breed_kitty(
             origin: OriginFor<T>,
             kitty1: KittyIndex,
@@ -154,55 +247,189 @@ pub mod pallet {
                 Error::<T>::KittyNotExist
             );
             ensure!(kitty1 != kitty2, Error::<T>::SameParentIndex);
+            Self::ensure_at_home(kitty1)?;
+            Self::ensure_at_home(kitty2)?;
             let dna = Self::generate_dna_from_2kitties(sender.clone(), kitty1, kitty2)?;
-            Self::create(sender, dna)
+            let kitty_id = Self::kitties_count();
+            Self::create(sender.clone(), dna)?;
+            Breeders::<T>::insert(kitty_id, sender);
+            Ok(().into())
         }
 
-        #[pallet::weight(10)]
+        /// Buy a listed kitty, paying at most `max_price`.
+        ///
+        /// The market fee, the breeder royalty and the seller's share are paid and
+        /// ownership moves to the buyer in one transaction.
+        #[pallet::weight(T::WeightInfo::buy_kitty())]
+        #[transactional]
         pub fn buy_kitty(
             origin: OriginFor<T>,
             kitty_id: KittyIndex,
-            kitty_price: T::Balance,
+            max_price: T::Balance,
         ) -> DispatchResultWithPostInfo {
             let buyer = ensure_signed(origin)?;
             let kitty_owner = Owner::<T>::get(kitty_id).ok_or(Error::<T>::KittyNotExist)?;
             ensure!(&buyer != &kitty_owner, Error::<T>::NotKittyOwner);
+            Self::ensure_at_home(kitty_id)?;
+            let price = KittyPrices::<T>::get(kitty_id).ok_or(Error::<T>::KittyNotForSale)?;
+            ensure!(price <= max_price, Error::<T>::KittyPriceTooHigh);
 
-            <pallet_balances::Pallet<T> as Currency<_>>::transfer(
-                &buyer,
-                &kitty_owner,
-                kitty_price,
-                ExistenceRequirement::KeepAlive,
-            )?;
+            let fee = T::MarketFee::get() * price;
+            let breeder = Breeders::<T>::get(kitty_id).filter(|breeder| breeder != &kitty_owner);
+            let royalty = match breeder {
+                Some(_) => T::BreederRoyalty::get() * price,
+                None => Zero::zero(),
+            };
+            let seller_share = price
+                .checked_sub(&fee)
+                .and_then(|rest| rest.checked_sub(&royalty))
+                .ok_or(Error::<T>::FeesExceedPrice)?;
+
+            Self::pay(&buyer, &T::Treasury::get(), fee)?;
+            if let Some(breeder) = breeder {
+                Self::pay(&buyer, &breeder, royalty)?;
+                Self::deposit_event(Event::RoyaltyPaid(breeder, kitty_id, royalty));
+            }
+            Self::pay(&buyer, &kitty_owner, seller_share)?;
 
+            Owner::<T>::insert(kitty_id, buyer.clone());
+            KittyPrices::<T>::remove(kitty_id);
+            Self::deposit_event(Event::Bought(buyer, kitty_owner, kitty_id, price));
             Ok(().into())
         }
 
-        #[pallet::weight(10)]
+        /// List a kitty for sale at `price`, or delist it with `None`.
+        #[pallet::weight(T::WeightInfo::sell_kitty())]
         pub fn sell_kitty(
             origin: OriginFor<T>,
-            buyer: T::AccountId,
             kitty_id: KittyIndex,
-            kitty_price: T::Balance,
+            price: Option<T::Balance>,
         ) -> DispatchResultWithPostInfo {
             let seller = ensure_signed(origin)?;
             ensure!(
                 Some(seller.clone()) == Owner::<T>::get(kitty_id),
                 Error::<T>::NotKittyOwner
             );
+            Self::ensure_at_home(kitty_id)?;
 
-            <pallet_balances::Pallet<T> as Currency<_>>::transfer(
-                &buyer,
-                &seller,
-                kitty_price,
-                ExistenceRequirement::KeepAlive,
-            )?;
+            match price {
+                Some(price) => KittyPrices::<T>::insert(kitty_id, price),
+                None => KittyPrices::<T>::remove(kitty_id),
+            }
+            Self::deposit_event(Event::PriceSet(seller, kitty_id, price));
+            Ok(().into())
+        }
 
+        #[pallet::weight(T::WeightInfo::send_on_adventure())]
+        pub fn send_on_adventure(
+            origin: OriginFor<T>,
+            kitty_id: KittyIndex,
+            duration: T::BlockNumber,
+        ) -> DispatchResultWithPostInfo {
+            let sender = ensure_signed(origin)?;
+            ensure!(
+                Some(sender.clone()) == Owner::<T>::get(kitty_id),
+                Error::<T>::NotKittyOwner
+            );
+            ensure!(
+                !duration.is_zero() && duration <= T::MaxAdventureDuration::get(),
+                Error::<T>::InvalidAdventureDuration
+            );
+            Self::ensure_at_home(kitty_id)?;
+
+            let started_at = <frame_system::Pallet<T>>::block_number();
+            Adventures::<T>::insert(
+                kitty_id,
+                Adventure {
+                    started_at,
+                    duration,
+                },
+            );
+            Self::deposit_event(Event::AdventureStarted(
+                sender,
+                kitty_id,
+                started_at.saturating_add(duration),
+            ));
+            Ok(().into())
+        }
+
+        #[pallet::weight(T::WeightInfo::claim_adventure())]
+        pub fn claim_adventure(
+            origin: OriginFor<T>,
+            kitty_id: KittyIndex,
+        ) -> DispatchResultWithPostInfo {
+            let sender = ensure_signed(origin)?;
+            ensure!(
+                Some(sender.clone()) == Owner::<T>::get(kitty_id),
+                Error::<T>::NotKittyOwner
+            );
+            let adventure =
+                Adventures::<T>::get(kitty_id).ok_or(Error::<T>::KittyNotOnAdventure)?;
+            ensure!(
+                <frame_system::Pallet<T>>::block_number()
+                    >= adventure.started_at.saturating_add(adventure.duration),
+                Error::<T>::AdventureNotFinished
+            );
+            let kitty = Kitties::<T>::get(kitty_id).ok_or(Error::<T>::KittyNotExist)?;
+
+            // The pot is never reaped, so a short pot pays out what it has left.
+            let pot = Self::account_id();
+            let available = <pallet_balances::Pallet<T> as Currency<_>>::free_balance(&pot)
+                .saturating_sub(<pallet_balances::Pallet<T> as Currency<_>>::minimum_balance());
+            let reward = Self::adventure_reward(&kitty, adventure.duration).min(available);
+            if !reward.is_zero() {
+                <pallet_balances::Pallet<T> as Currency<_>>::transfer(
+                    &pot,
+                    &sender,
+                    reward,
+                    ExistenceRequirement::KeepAlive,
+                )?;
+            }
+
+            Adventures::<T>::remove(kitty_id);
+            Self::deposit_event(Event::AdventureClaimed(sender, kitty_id, reward));
             Ok(().into())
         }
     }
 
     impl<T: Config> Pallet<T> {
+        /// The account holding the adventure reward pot.
+        pub fn account_id() -> T::AccountId {
+            T::PalletId::get().into_account()
+        }
+
+        /// The adventure stat of a kitty, between 1 and 100, derived from its DNA.
+        pub fn adventure_stat(kitty: &Kitty) -> u8 {
+            kitty.0[0] % 100 + 1
+        }
+
+        /// Reward for an adventure of `duration` blocks, scaled by the kitty's stat.
+        pub fn adventure_reward(kitty: &Kitty, duration: T::BlockNumber) -> T::Balance {
+            let blocks: T::Balance = duration.saturated_into::<u32>().into();
+            let full = T::AdventureRewardPerBlock::get().saturating_mul(blocks);
+            Percent::from_percent(Self::adventure_stat(kitty)) * full
+        }
+
+        fn pay(from: &T::AccountId, to: &T::AccountId, amount: T::Balance) -> DispatchResult {
+            if amount.is_zero() {
+                return Ok(());
+            }
+            <pallet_balances::Pallet<T> as Currency<_>>::transfer(
+                from,
+                to,
+                amount,
+                ExistenceRequirement::KeepAlive,
+            )
+        }
+
+        fn ensure_at_home(kitty_id: KittyIndex) -> DispatchResult {
+            ensure!(
+                !Adventures::<T>::contains_key(kitty_id),
+                Error::<T>::KittyOnAdventure
+            );
+            Ok(())
+        }
+
         fn random_hash(sender: &T::AccountId) -> [u8; 16] {
             let payload = (
                 T::Randomness::random_seed(),
@@ -213,21 +440,15 @@ pub mod pallet {
         }
 
         fn create(kitty_owner: T::AccountId, dna: [u8; 16]) -> DispatchResultWithPostInfo {
-            let kitty_id = match Self::kitties_count() {
-                Some(count) => {
-                    ensure!(
-                        count != KittyIndex::max_value(),
-                        Error::<T>::KittyCountOverflow
-                    );
-                    count
-                }
-                None => 0,
-            };
-           
+            let kitty_id = Self::kitties_count();
+            ensure!(
+                kitty_id != KittyIndex::max_value(),
+                Error::<T>::KittyCountOverflow
+            );
 
-            Kitties::<T>::insert(kitty_id, Some(Kitty(dna)));
-            Owner::<T>::insert(kitty_id, Some(kitty_owner.clone()));
-            <KittiesCount<T>>::put(Some(kitty_id + 1));
+            Kitties::<T>::insert(kitty_id, Kitty(dna));
+            Owner::<T>::insert(kitty_id, kitty_owner.clone());
+            <KittiesCount<T>>::put(kitty_id + 1);
             Self::deposit_event(Event::KittyCreated(kitty_owner, kitty_id));
             Ok(().into())
         }
@@ -0,0 +1,135 @@
+//! Property-based tests: random sequences of kitty calls must keep ownership and
+//! balances consistent after every step.
+
+use crate::{mock::*, *};
+use frame_support::traits::Currency;
+use proptest::prelude::*;
+
+const ACCOUNTS: u64 = 4;
+const INITIAL_BALANCE: u128 = 1_000_000;
+const POT_BALANCE: u128 = 100_000;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Create(u64),
+    Transfer(KittyIndex, u64),
+    Breed(u64, KittyIndex, KittyIndex),
+    Sell(KittyIndex, Option<u128>),
+    Buy(u64, KittyIndex, u128),
+    SendOnAdventure(KittyIndex, u64),
+    ClaimAdventure(KittyIndex),
+    NextBlocks(u64),
+}
+
+fn account() -> impl Strategy<Value = u64> {
+    1..=ACCOUNTS
+}
+
+// Includes ids that do not exist yet, so failing calls are exercised too.
+fn kitty() -> impl Strategy<Value = KittyIndex> {
+    0..8u64
+}
+
+fn price() -> impl Strategy<Value = u128> {
+    0..20_000u128
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        account().prop_map(Op::Create),
+        (kitty(), account()).prop_map(|(kitty_id, dest)| Op::Transfer(kitty_id, dest)),
+        (account(), kitty(), kitty()).prop_map(|(who, k1, k2)| Op::Breed(who, k1, k2)),
+        (kitty(), proptest::option::of(price()))
+            .prop_map(|(kitty_id, price)| Op::Sell(kitty_id, price)),
+        (account(), kitty(), price())
+            .prop_map(|(buyer, kitty_id, max)| Op::Buy(buyer, kitty_id, max)),
+        (kitty(), 0..120u64).prop_map(|(kitty_id, duration)| Op::SendOnAdventure(
+            kitty_id, duration
+        )),
+        kitty().prop_map(Op::ClaimAdventure),
+        (1..50u64).prop_map(Op::NextBlocks),
+    ]
+}
+
+/// Calls on an existing kitty are sent by its owner, so most of them can succeed.
+fn owner_of(kitty_id: KittyIndex) -> u64 {
+    Owner::<Test>::get(kitty_id).unwrap_or(ACCOUNTS)
+}
+
+fn apply(op: Op) {
+    // Failures are expected; the invariants must hold either way.
+    let _ = match op {
+        Op::Create(who) => KittiesModule::create_kitty(Origin::signed(who)),
+        Op::Transfer(kitty_id, dest) => {
+            KittiesModule::transfer_kitty(Origin::signed(owner_of(kitty_id)), dest, kitty_id)
+        }
+        Op::Breed(who, kitty1, kitty2) => {
+            KittiesModule::breed_kitty(Origin::signed(who), kitty1, kitty2)
+        }
+        Op::Sell(kitty_id, price) => {
+            KittiesModule::sell_kitty(Origin::signed(owner_of(kitty_id)), kitty_id, price)
+        }
+        Op::Buy(buyer, kitty_id, max_price) => {
+            KittiesModule::buy_kitty(Origin::signed(buyer), kitty_id, max_price)
+        }
+        Op::SendOnAdventure(kitty_id, duration) => KittiesModule::send_on_adventure(
+            Origin::signed(owner_of(kitty_id)),
+            kitty_id,
+            duration,
+        ),
+        Op::ClaimAdventure(kitty_id) => {
+            KittiesModule::claim_adventure(Origin::signed(owner_of(kitty_id)), kitty_id)
+        }
+        Op::NextBlocks(n) => {
+            System::set_block_number(System::block_number() + n);
+            Ok(().into())
+        }
+    };
+}
+
+fn check_invariants(total_issuance: u128) {
+    let count = KittiesCount::<Test>::get();
+
+    // `KittiesCount` matches the stored kitties and every one of them has exactly one owner.
+    assert_eq!(Kitties::<Test>::iter().count() as u64, count);
+    assert_eq!(Owner::<Test>::iter().count() as u64, count);
+    for kitty_id in 0..count {
+        assert!(Kitties::<Test>::contains_key(kitty_id));
+        let owner = Owner::<Test>::get(kitty_id).expect("kitty lost its owner");
+        assert!((1..=ACCOUNTS).contains(&owner));
+    }
+
+    // Listings and adventures only refer to existing kitties.
+    assert!(KittyPrices::<Test>::iter().all(|(kitty_id, _)| kitty_id < count));
+    assert!(Adventures::<Test>::iter().all(|(kitty_id, _)| kitty_id < count));
+
+    // Trades, fees, royalties and rewards only move funds around.
+    assert_eq!(Balances::total_issuance(), total_issuance);
+    let held: u128 = (1..=ACCOUNTS)
+        .map(|who| Balances::total_balance(&who))
+        .sum::<u128>()
+        + Balances::total_balance(&KittiesModule::account_id());
+    assert_eq!(held, total_issuance);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn random_calls_keep_invariants(ops in prop::collection::vec(op(), 1..60)) {
+        new_test_ext().execute_with(|| {
+            System::set_block_number(1);
+            for who in 1..=ACCOUNTS {
+                Balances::make_free_balance_be(&who, INITIAL_BALANCE);
+            }
+            Balances::make_free_balance_be(&KittiesModule::account_id(), POT_BALANCE);
+            let total_issuance = Balances::total_issuance();
+            check_invariants(total_issuance);
+
+            for op in ops {
+                apply(op);
+                check_invariants(total_issuance);
+            }
+        });
+    }
+}
@@ -1,6 +1,11 @@
 use super::*;
 use crate::{mock::*, Error};
-use frame_support::{assert_noop, assert_ok};
+use codec::Encode;
+use frame_support::{
+    assert_noop, assert_ok,
+    storage::unhashed,
+    traits::{GetStorageVersion, Hooks, StorageVersion},
+};
 #[test]
 fn test_create_kitty_success() {
     new_test_ext().execute_with(|| {
@@ -14,7 +19,7 @@ fn test_transfer_kitty_success() {
     new_test_ext().execute_with(|| {
         System::set_block_number(10);
         KittiesModule::create_kitty(Origin::signed(1));
-        let kittyid = <KittiesCount<Test>>::get().unwrap() - 1;
+        let kittyid = <KittiesCount<Test>>::get() - 1;
 
         assert_ok!(KittiesModule::transfer_kitty(Origin::signed(1), 2, kittyid));
         assert_eq!(Owner::<Test>::get(kittyid), Some(2));
@@ -36,7 +41,7 @@ fn test_transfer_kitty_failed_dueto_not_kitty_owner() {
     new_test_ext().execute_with(|| {
         System::set_block_number(10);
         KittiesModule::create_kitty(Origin::signed(1));
-        let next_kittyid = <KittiesCount<Test>>::get().unwrap();
+        let next_kittyid = <KittiesCount<Test>>::get();
 
         assert_noop!(
             KittiesModule::transfer_kitty(Origin::signed(10), 2, next_kittyid - 1),
@@ -84,5 +89,327 @@ fn test_breed_kitty_failed_dueto_kitty_same_parent() {
 
 fn create_kitty(owner: u64) -> u64 {
     KittiesModule::create_kitty(Origin::signed(owner));
-    <KittiesCount<Test>>::get().unwrap() - 1
+    <KittiesCount<Test>>::get() - 1
+}
+
+#[test]
+fn test_runtime_upgrade_migrates_v0_storage() {
+    new_test_ext().execute_with(|| {
+        StorageVersion::new(0).put::<KittiesModule>();
+        // Layout before version 1: every value wrapped in an extra `Option`.
+        unhashed::put(&Kitties::<Test>::hashed_key_for(0), &Some(Kitty([1u8; 16])));
+        unhashed::put(&Kitties::<Test>::hashed_key_for(1), &Some(Kitty([2u8; 16])));
+        unhashed::put(&Owner::<Test>::hashed_key_for(0), &Some(1u64));
+        unhashed::put(&Owner::<Test>::hashed_key_for(1), &Some(2u64));
+        unhashed::put(&KittiesCount::<Test>::hashed_key(), &Some(2u64));
+
+        KittiesModule::on_runtime_upgrade();
+
+        assert_eq!(KittiesModule::on_chain_storage_version(), 1);
+        assert_eq!(Kitties::<Test>::get(0), Some(Kitty([1u8; 16])));
+        assert_eq!(Kitties::<Test>::get(1), Some(Kitty([2u8; 16])));
+        assert_eq!(Owner::<Test>::get(0), Some(1));
+        assert_eq!(Owner::<Test>::get(1), Some(2));
+        assert_eq!(KittiesCount::<Test>::get(), 2);
+        assert_eq!(
+            unhashed::get_raw(&Kitties::<Test>::hashed_key_for(0)),
+            Some(Kitty([1u8; 16]).encode())
+        );
+
+        // New kitties continue from the migrated count.
+        assert_eq!(create_kitty(1), 2);
+    });
+}
+
+#[test]
+fn test_runtime_upgrade_is_noop_on_current_version() {
+    new_test_ext().execute_with(|| {
+        StorageVersion::new(1).put::<KittiesModule>();
+        let kitty_id = create_kitty(1);
+        let raw = unhashed::get_raw(&Kitties::<Test>::hashed_key_for(kitty_id));
+
+        assert_eq!(KittiesModule::on_runtime_upgrade(), 0);
+        assert_eq!(
+            unhashed::get_raw(&Kitties::<Test>::hashed_key_for(kitty_id)),
+            raw
+        );
+        assert_eq!(KittiesCount::<Test>::get(), 1);
+    });
+}
+
+fn fund_adventure_pot(amount: u128) {
+    Balances::make_free_balance_be(&KittiesModule::account_id(), amount);
+}
+
+#[test]
+fn test_adventure_claim_pays_reward() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        fund_adventure_pot(1_000_000);
+        Balances::make_free_balance_be(&1, 1_000);
+        let kitty_id = create_kitty(1);
+        assert_ok!(KittiesModule::send_on_adventure(
+            Origin::signed(1),
+            kitty_id,
+            20
+        ));
+
+        System::set_block_number(30);
+        let kitty = Kitties::<Test>::get(kitty_id).unwrap();
+        let reward = KittiesModule::adventure_reward(&kitty, 20);
+        assert!(reward > 0);
+        assert_ok!(KittiesModule::claim_adventure(Origin::signed(1), kitty_id));
+        assert_eq!(Balances::free_balance(1), 1_000 + reward);
+        assert_eq!(Adventures::<Test>::get(kitty_id), None);
+    });
+}
+
+#[test]
+fn test_adventure_reward_scales_with_duration_and_stat() {
+    new_test_ext().execute_with(|| {
+        let weak = Kitty([0u8; 16]);
+        let strong = Kitty([99u8; 16]);
+        assert_eq!(KittiesModule::adventure_stat(&weak), 1);
+        assert_eq!(KittiesModule::adventure_stat(&strong), 100);
+        assert_eq!(KittiesModule::adventure_reward(&strong, 10), 100);
+        assert_eq!(KittiesModule::adventure_reward(&strong, 20), 200);
+        assert_eq!(KittiesModule::adventure_reward(&weak, 100), 10);
+    });
+}
+
+#[test]
+fn test_adventure_claim_failed_dueto_not_finished() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let kitty_id = create_kitty(1);
+        assert_ok!(KittiesModule::send_on_adventure(
+            Origin::signed(1),
+            kitty_id,
+            20
+        ));
+
+        System::set_block_number(29);
+        assert_noop!(
+            KittiesModule::claim_adventure(Origin::signed(1), kitty_id),
+            Error::<Test>::AdventureNotFinished
+        );
+    });
+}
+
+#[test]
+fn test_adventure_failed_dueto_invalid_duration() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let kitty_id = create_kitty(1);
+        assert_noop!(
+            KittiesModule::send_on_adventure(Origin::signed(1), kitty_id, 0),
+            Error::<Test>::InvalidAdventureDuration
+        );
+        assert_noop!(
+            KittiesModule::send_on_adventure(Origin::signed(1), kitty_id, 101),
+            Error::<Test>::InvalidAdventureDuration
+        );
+    });
+}
+
+#[test]
+fn test_adventure_failed_dueto_not_kitty_owner() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let kitty_id = create_kitty(1);
+        assert_noop!(
+            KittiesModule::send_on_adventure(Origin::signed(2), kitty_id, 10),
+            Error::<Test>::NotKittyOwner
+        );
+    });
+}
+
+#[test]
+fn test_kitty_on_adventure_is_locked() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let kitty_id = create_kitty(1);
+        let other = create_kitty(1);
+        assert_ok!(KittiesModule::send_on_adventure(
+            Origin::signed(1),
+            kitty_id,
+            10
+        ));
+
+        assert_noop!(
+            KittiesModule::send_on_adventure(Origin::signed(1), kitty_id, 10),
+            Error::<Test>::KittyOnAdventure
+        );
+        assert_noop!(
+            KittiesModule::transfer_kitty(Origin::signed(1), 2, kitty_id),
+            Error::<Test>::KittyOnAdventure
+        );
+        assert_noop!(
+            KittiesModule::breed_kitty(Origin::signed(1), kitty_id, other),
+            Error::<Test>::KittyOnAdventure
+        );
+        assert_noop!(
+            KittiesModule::buy_kitty(Origin::signed(2), kitty_id, 100),
+            Error::<Test>::KittyOnAdventure
+        );
+    });
+}
+
+#[test]
+fn test_adventure_claim_with_empty_pot_unlocks_kitty() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let kitty_id = create_kitty(1);
+        assert_ok!(KittiesModule::send_on_adventure(
+            Origin::signed(1),
+            kitty_id,
+            10
+        ));
+
+        System::set_block_number(20);
+        assert_ok!(KittiesModule::claim_adventure(Origin::signed(1), kitty_id));
+        assert_eq!(Balances::free_balance(1), 0);
+        assert_ok!(KittiesModule::transfer_kitty(Origin::signed(1), 2, kitty_id));
+    });
+}
+
+#[test]
+fn test_sell_kitty_lists_and_delists() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let kitty_id = create_kitty(1);
+        assert_ok!(KittiesModule::sell_kitty(
+            Origin::signed(1),
+            kitty_id,
+            Some(1_000)
+        ));
+        assert_eq!(KittyPrices::<Test>::get(kitty_id), Some(1_000));
+
+        assert_ok!(KittiesModule::sell_kitty(Origin::signed(1), kitty_id, None));
+        assert_eq!(KittyPrices::<Test>::get(kitty_id), None);
+    });
+}
+
+#[test]
+fn test_sell_kitty_failed_dueto_not_kitty_owner() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let kitty_id = create_kitty(1);
+        assert_noop!(
+            KittiesModule::sell_kitty(Origin::signed(2), kitty_id, Some(1_000)),
+            Error::<Test>::NotKittyOwner
+        );
+    });
+}
+
+#[test]
+fn test_buy_kitty_pays_fee_royalty_and_moves_ownership() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let parent1 = create_kitty(1);
+        let parent2 = create_kitty(1);
+        assert_ok!(KittiesModule::breed_kitty(
+            Origin::signed(1),
+            parent1,
+            parent2
+        ));
+        let kitty_id = <KittiesCount<Test>>::get() - 1;
+        assert_eq!(Breeders::<Test>::get(kitty_id), Some(1));
+        assert_ok!(KittiesModule::transfer_kitty(Origin::signed(1), 2, kitty_id));
+
+        Balances::make_free_balance_be(&1, 1_000);
+        Balances::make_free_balance_be(&2, 1_000);
+        Balances::make_free_balance_be(&3, 100_000);
+        fund_adventure_pot(1_000);
+        assert_ok!(KittiesModule::sell_kitty(
+            Origin::signed(2),
+            kitty_id,
+            Some(10_000)
+        ));
+        assert_ok!(KittiesModule::buy_kitty(Origin::signed(3), kitty_id, 10_000));
+
+        assert_eq!(Owner::<Test>::get(kitty_id), Some(3));
+        assert_eq!(KittyPrices::<Test>::get(kitty_id), None);
+        assert_eq!(Balances::free_balance(3), 90_000);
+        assert_eq!(Balances::free_balance(KittiesModule::account_id()), 1_500);
+        assert_eq!(Balances::free_balance(1), 2_000);
+        assert_eq!(Balances::free_balance(2), 9_500);
+    });
+}
+
+#[test]
+fn test_buy_kitty_without_breeder_pays_no_royalty() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let kitty_id = create_kitty(1);
+        Balances::make_free_balance_be(&1, 1_000);
+        Balances::make_free_balance_be(&2, 100_000);
+        fund_adventure_pot(1_000);
+        assert_ok!(KittiesModule::sell_kitty(
+            Origin::signed(1),
+            kitty_id,
+            Some(10_000)
+        ));
+        assert_ok!(KittiesModule::buy_kitty(Origin::signed(2), kitty_id, 20_000));
+
+        assert_eq!(Owner::<Test>::get(kitty_id), Some(2));
+        assert_eq!(Balances::free_balance(2), 90_000);
+        assert_eq!(Balances::free_balance(KittiesModule::account_id()), 1_500);
+        assert_eq!(Balances::free_balance(1), 10_500);
+    });
+}
+
+#[test]
+fn test_market_fee_and_breeder_royalty_fit_in_the_price() {
+    <KittiesModule as Hooks<u64>>::integrity_test();
+}
+
+#[test]
+fn test_buy_kitty_failed_dueto_not_for_sale() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let kitty_id = create_kitty(1);
+        assert_noop!(
+            KittiesModule::buy_kitty(Origin::signed(2), kitty_id, 10_000),
+            Error::<Test>::KittyNotForSale
+        );
+    });
+}
+
+#[test]
+fn test_buy_kitty_failed_dueto_price_too_high() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let kitty_id = create_kitty(1);
+        assert_ok!(KittiesModule::sell_kitty(
+            Origin::signed(1),
+            kitty_id,
+            Some(10_000)
+        ));
+        assert_noop!(
+            KittiesModule::buy_kitty(Origin::signed(2), kitty_id, 9_999),
+            Error::<Test>::KittyPriceTooHigh
+        );
+    });
+}
+
+#[test]
+fn test_buy_kitty_is_atomic_when_seller_payment_fails() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let kitty_id = create_kitty(1);
+        Balances::make_free_balance_be(&2, 1_000);
+        fund_adventure_pot(1_000);
+        // The seller share (285) alone is below the existential deposit of account 1.
+        assert_ok!(KittiesModule::sell_kitty(
+            Origin::signed(1),
+            kitty_id,
+            Some(300)
+        ));
+        assert!(KittiesModule::buy_kitty(Origin::signed(2), kitty_id, 300).is_err());
+
+        assert_eq!(Owner::<Test>::get(kitty_id), Some(1));
+        assert_eq!(Balances::free_balance(2), 1_000);
+        assert_eq!(Balances::free_balance(KittiesModule::account_id()), 1_000);
+    });
 }
@@ -12,7 +12,8 @@ use sp_api::impl_runtime_apis;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
 use sp_runtime::traits::{
-    AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, Verify,
+    AccountIdConversion, AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor,
+    Verify,
 };
 use sp_runtime::{
     create_runtime_str, generic, impl_opaque_keys,
@@ -32,7 +33,7 @@ pub use frame_support::{
         constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
         IdentityFee, Weight,
     },
-    StorageValue,
+    PalletId, StorageValue,
 };
 pub use pallet_balances::Call as BalancesCall;
 pub use pallet_timestamp::Call as TimestampCall;
@@ -275,10 +276,27 @@ impl pallet_template::Config for Runtime {
     type Event = Event;
 }
 
+parameter_types! {
+    pub const KittiesPalletId: PalletId = PalletId(*b"py/kitty");
+    pub const AdventureRewardPerBlock: Balance = 1_000;
+    pub const MaxAdventureDuration: BlockNumber = 7 * DAYS;
+    pub const MarketFee: Permill = Permill::from_percent(2);
+    pub const BreederRoyalty: Permill = Permill::from_percent(5);
+    /// Market fees fund the adventure reward pot.
+    pub KittiesTreasury: AccountId = KittiesPalletId::get().into_account();
+}
+
 /// Configure the pallet-kitties in pallets/kitties.
 impl pallet_kitties::Config for Runtime {
     type Event = Event;
     type Randomness = RandomnessCollectiveFlip;
+    type PalletId = KittiesPalletId;
+    type AdventureRewardPerBlock = AdventureRewardPerBlock;
+    type MaxAdventureDuration = MaxAdventureDuration;
+    type MarketFee = MarketFee;
+    type BreederRoyalty = BreederRoyalty;
+    type Treasury = KittiesTreasury;
+    type WeightInfo = pallet_kitties::weights::SubstrateWeight<Runtime>;
 }
 // Create the runtime by composing the FRAME pallets that were previously configured.
 construct_runtime!(
@@ -484,6 +502,7 @@ impl_runtime_apis! {
             add_benchmark!(params, batches, pallet_balances, Balances);
             add_benchmark!(params, batches, pallet_timestamp, Timestamp);
             add_benchmark!(params, batches, pallet_template, TemplateModule);
+            add_benchmark!(params, batches, pallet_kitties, KittiesModule);
 
             if batches.is_empty() { return Err("Benchmark not found for this pallet.".into()) }
             Ok(batches)
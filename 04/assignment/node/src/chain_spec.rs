@@ -1,6 +1,6 @@
 use node_template_runtime::{
-	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, Signature, SudoConfig,
-	SystemConfig, WASM_BINARY,
+	pallet_ocw::PriceSource, AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig,
+	OcwDemoConfig, Signature, SudoConfig, SystemConfig, WASM_BINARY,
 };
 use sc_service::ChainType;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
@@ -150,5 +150,17 @@ fn testnet_genesis(
 			// Assign network admin rights.
 			key: root_key,
 		},
+		ocw_demo: OcwDemoConfig {
+			// Start with a DOT price feed.
+			assets: vec![(
+				b"DOT".to_vec(),
+				vec![
+					PriceSource::CoinCap { asset_id: b"polkadot".to_vec() },
+					PriceSource::CryptoCompare { symbol: b"DOT".to_vec() },
+				],
+			)],
+			// The node's offchain worker signs prices with the `//Alice` key, see `service.rs`.
+			oracles: vec![get_account_id_from_seed::<sr25519::Public>("Alice")],
+		},
 	}
 }
@@ -8,6 +8,21 @@ pub struct Cli {
 
 	#[structopt(flatten)]
 	pub run: RunCmd,
+
+	/// Override an offchain worker endpoint, e.g. `--ocw-endpoint github-url=http://localhost:8080/gh`.
+	///
+	/// Known names are `github-url`, `coincap-url`, `cryptocompare-url` and `user-agent`. The
+	/// value is written to offchain local storage at startup and kept until changed.
+	#[structopt(long = "ocw-endpoint", value_name = "NAME=VALUE", parse(try_from_str = parse_ocw_endpoint))]
+	pub ocw_endpoints: Vec<(String, String)>,
+}
+
+fn parse_ocw_endpoint(arg: &str) -> Result<(String, String), String> {
+	let (name, value) =
+		arg.split_once('=').ok_or_else(|| format!("expected NAME=VALUE, got `{}`", arg))?;
+	node_template_runtime::pallet_ocw::Endpoint::from_name(name)
+		.ok_or_else(|| format!("unknown offchain worker endpoint `{}`", name))?;
+	Ok((name.into(), value.into()))
 }
 
 #[derive(Debug, StructOpt)]
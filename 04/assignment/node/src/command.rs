@@ -127,10 +127,11 @@ pub fn run() -> sc_cli::Result<()> {
 			},
 		None => {
 			let runner = cli.create_runner(&cli.run)?;
+			let ocw_endpoints = cli.ocw_endpoints;
 			runner.run_node_until_exit(|config| async move {
 				match config.role {
 					Role::Light => service::new_light(config),
-					_ => service::new_full(config),
+					_ => service::new_full(config, ocw_endpoints),
 				}
 				.map_err(sc_cli::Error::Service)
 			})
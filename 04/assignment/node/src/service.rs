@@ -1,7 +1,7 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
 use node_template_runtime::{self as runtime, opaque::Block, RuntimeApi};
-use sc_client_api::{ExecutorProvider, RemoteBackend};
+use sc_client_api::{Backend, ExecutorProvider, RemoteBackend};
 use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
 use sc_executor::native_executor_instance;
 pub use sc_executor::NativeExecutor;
@@ -11,6 +11,7 @@ use sc_service::{error::Error as ServiceError, Configuration, TaskManager};
 use sc_telemetry::{Telemetry, TelemetryWorker};
 use sp_consensus::SlotData;
 use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
+use sp_core::offchain::{OffchainStorage, STORAGE_PREFIX};
 use std::{sync::Arc, time::Duration};
 
 // Our native executor instance.
@@ -150,8 +151,29 @@ fn remote_keystore(_url: &String) -> Result<Arc<LocalKeystore>, &'static str> {
 	Err("Remote Keystore not supported.")
 }
 
+/// Write the `--ocw-endpoint` overrides to the offchain persistent storage the offchain worker
+/// reads them from.
+fn set_ocw_endpoints(backend: &FullBackend, endpoints: &[(String, String)]) -> Result<(), ServiceError> {
+	if endpoints.is_empty() {
+		return Ok(())
+	}
+	let mut storage = backend
+		.offchain_storage()
+		.ok_or_else(|| ServiceError::Other("Offchain storage is not available.".into()))?;
+	for (name, value) in endpoints {
+		let endpoint = runtime::pallet_ocw::Endpoint::from_name(name).ok_or_else(|| {
+			ServiceError::Other(format!("Unknown offchain worker endpoint `{}`.", name))
+		})?;
+		storage.set(STORAGE_PREFIX, &endpoint.storage_key(), value.as_bytes());
+	}
+	Ok(())
+}
+
 /// Builds a new service for a full client.
-pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError> {
+pub fn new_full(
+	mut config: Configuration,
+	ocw_endpoints: Vec<(String, String)>,
+) -> Result<TaskManager, ServiceError> {
 	let sc_service::PartialComponents {
 		client,
 		backend,
@@ -174,6 +196,8 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		};
 	}
 
+	set_ocw_endpoints(&backend, &ocw_endpoints)?;
+
 	config.network.extra_sets.push(sc_finality_grandpa::grandpa_peers_set_config());
 
 	let (network, system_rpc_tx, network_starter) =
@@ -206,10 +230,15 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
+		let offchain_storage = backend.offchain_storage();
 
 		Box::new(move |deny_unsafe, _| {
-			let deps =
-				crate::rpc::FullDeps { client: client.clone(), pool: pool.clone(), deny_unsafe };
+			let deps = crate::rpc::FullDeps {
+				client: client.clone(),
+				pool: pool.clone(),
+				deny_unsafe,
+				offchain_storage: offchain_storage.clone(),
+			};
 
 			crate::rpc::create_full(deps)
 		})
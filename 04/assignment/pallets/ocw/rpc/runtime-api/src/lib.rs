@@ -0,0 +1,16 @@
+//! Runtime API definition for the price feeds of pallet-ocw.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use parity_scale_codec::Codec;
+use sp_std::prelude::*;
+
+sp_api::decl_runtime_apis! {
+	/// Read access to the finalized price feeds.
+	pub trait OcwApi<Price> where Price: Codec {
+		/// The latest finalized price of `asset`.
+		fn latest_price(asset: Vec<u8>) -> Option<Price>;
+		/// The time-weighted average price of `asset` over the last `window` milliseconds.
+		fn twap(asset: Vec<u8>, window: u64) -> Option<Price>;
+	}
+}
@@ -0,0 +1,140 @@
+//! RPC interface for the price feeds of pallet-ocw.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use pallet_ocw::indexing::{read_history, IndexingData};
+use parity_scale_codec::{Codec, Decode, Encode};
+use sc_rpc_api::DenyUnsafe;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::offchain::{OffchainStorage, STORAGE_PREFIX};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{AtLeast32BitUnsigned, Block as BlockT},
+};
+
+pub use pallet_ocw_rpc_runtime_api::OcwApi as OcwRuntimeApi;
+
+/// Price feed RPC methods.
+///
+/// Assets are given by their symbol, e.g. `"DOT"`.
+#[rpc]
+pub trait OcwApi<BlockHash, Price> {
+	/// The latest finalized price of `asset`.
+	#[rpc(name = "ocw_getPrice")]
+	fn get_price(&self, asset: String, at: Option<BlockHash>) -> Result<Option<Price>>;
+
+	/// The time-weighted average price of `asset` over the last `window` milliseconds.
+	#[rpc(name = "ocw_getTwap")]
+	fn get_twap(&self, asset: String, window: u64, at: Option<BlockHash>) -> Result<Option<Price>>;
+}
+
+/// Implements [`OcwApi`] by calling into the runtime.
+pub struct Ocw<C, Block> {
+	client: Arc<C>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> Ocw<C, Block> {
+	/// Create a new `Ocw` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+/// Offchain indexed price history RPC methods.
+///
+/// The history is only kept by nodes started with `--enable-offchain-indexing true`.
+#[rpc]
+pub trait OcwIndexApi<BlockNumber, AccountId> {
+	/// The indexed prices of `asset` accepted from block `from` to block `to`, oldest first.
+	///
+	/// At most [`MAX_INDEXED_BLOCKS`] blocks can be read at once. Unsafe: denied unless the
+	/// node allows unsafe RPC methods.
+	#[rpc(name = "ocw_getIndexedPrices")]
+	fn get_indexed_prices(
+		&self,
+		asset: String,
+		from: BlockNumber,
+		to: BlockNumber,
+	) -> Result<Vec<(BlockNumber, IndexingData<AccountId>)>>;
+}
+
+/// The largest number of blocks `ocw_getIndexedPrices` reads.
+pub const MAX_INDEXED_BLOCKS: u32 = 10_000;
+
+/// Implements [`OcwIndexApi`] by reading the node's offchain storage.
+pub struct OcwIndex<S> {
+	storage: S,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl<S> OcwIndex<S> {
+	/// Create a new `OcwIndex` reading from `storage`.
+	pub fn new(storage: S, deny_unsafe: DenyUnsafe) -> Self {
+		Self { storage, deny_unsafe }
+	}
+}
+
+/// Error code for failed runtime calls.
+const RUNTIME_ERROR: i64 = 1;
+/// Error code for block ranges larger than [`MAX_INDEXED_BLOCKS`].
+const RANGE_TOO_LARGE: i64 = 2;
+
+fn runtime_error(err: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: "Unable to query the price feed.".into(),
+		data: Some(format!("{:?}", err).into()),
+	}
+}
+
+impl<C, Block, Price> OcwApi<<Block as BlockT>::Hash, Price> for Ocw<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: OcwRuntimeApi<Block, Price>,
+	Price: Codec + Send + Sync + 'static,
+{
+	fn get_price(&self, asset: String, at: Option<<Block as BlockT>::Hash>) -> Result<Option<Price>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		self.client.runtime_api().latest_price(&at, asset.into_bytes()).map_err(runtime_error)
+	}
+
+	fn get_twap(
+		&self,
+		asset: String,
+		window: u64,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<Price>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		self.client.runtime_api().twap(&at, asset.into_bytes(), window).map_err(runtime_error)
+	}
+}
+
+impl<S, BlockNumber, AccountId> OcwIndexApi<BlockNumber, AccountId> for OcwIndex<S>
+where
+	S: OffchainStorage + 'static,
+	BlockNumber: AtLeast32BitUnsigned + Encode + Copy + Send + Sync + 'static,
+	AccountId: Decode + Send + Sync + 'static,
+{
+	fn get_indexed_prices(
+		&self,
+		asset: String,
+		from: BlockNumber,
+		to: BlockNumber,
+	) -> Result<Vec<(BlockNumber, IndexingData<AccountId>)>> {
+		self.deny_unsafe.check_if_safe()?;
+		if to.saturating_sub(from) >= MAX_INDEXED_BLOCKS.into() {
+			return Err(RpcError {
+				code: ErrorCode::ServerError(RANGE_TOO_LARGE),
+				message: format!("At most {} blocks can be read at once.", MAX_INDEXED_BLOCKS),
+				data: None,
+			});
+		}
+
+		Ok(read_history(asset.as_bytes(), from, to, |key| self.storage.get(STORAGE_PREFIX, key)))
+	}
+}
@@ -0,0 +1,71 @@
+//! The HTTP endpoints and `User-Agent` the offchain worker uses.
+//!
+//! Each one has a compiled default that a node can override by writing the new value, as raw
+//! UTF-8 bytes, to offchain persistent storage under `offchain-demo::config::<name>`. The node's
+//! `--ocw-endpoint <name>=<value>` flag does this at startup, and the `offchain_localStorageSet`
+//! RPC does it on a running node:
+//!
+//! ```text
+//! offchain_localStorageSet("PERSISTENT", hex("offchain-demo::config::github-url"), hex("http://localhost:8080/gh"))
+//! ```
+
+use sp_core::offchain::StorageKind;
+use sp_runtime::RuntimeDebug;
+use sp_std::{prelude::*, str};
+
+const KEY_PREFIX: &[u8] = b"offchain-demo::config::";
+
+/// A configurable endpoint.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Endpoint {
+	/// The github organisation fetched by the `GithubInfo` task.
+	GithubUrl,
+	/// The coincap asset API, the coincap asset id is appended to it.
+	CoinCapUrl,
+	/// The cryptocompare price API, `?fsym=<symbol>&tsyms=USD` is appended to it.
+	CryptoCompareUrl,
+	/// The `User-Agent` header sent with every request.
+	UserAgent,
+}
+
+impl Endpoint {
+	pub const ALL: [Endpoint; 4] =
+		[Endpoint::GithubUrl, Endpoint::CoinCapUrl, Endpoint::CryptoCompareUrl, Endpoint::UserAgent];
+
+	/// The name used in the storage key and on the command line.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Endpoint::GithubUrl => "github-url",
+			Endpoint::CoinCapUrl => "coincap-url",
+			Endpoint::CryptoCompareUrl => "cryptocompare-url",
+			Endpoint::UserAgent => "user-agent",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL.iter().copied().find(|endpoint| endpoint.name() == name)
+	}
+
+	pub fn default_value(&self) -> &'static str {
+		match self {
+			// We are fetching information from the github public API about organization`substrate-developer-hub`.
+			Endpoint::GithubUrl => "https://api.github.com/orgs/substrate-developer-hub",
+			Endpoint::CoinCapUrl => "https://api.coincap.io/v2/assets/",
+			Endpoint::CryptoCompareUrl => "https://min-api.cryptocompare.com/data/price",
+			Endpoint::UserAgent => "jimmychu0807",
+		}
+	}
+
+	/// The offchain persistent storage key holding the override.
+	pub fn storage_key(&self) -> Vec<u8> {
+		[KEY_PREFIX, self.name().as_bytes()].concat()
+	}
+
+	/// The override in offchain persistent storage if there is a valid UTF-8 one, the default
+	/// otherwise. Only callable from an offchain worker.
+	pub fn get(&self) -> Vec<u8> {
+		sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, &self.storage_key())
+			.filter(|value| str::from_utf8(value).is_ok())
+			.unwrap_or_else(|| self.default_value().as_bytes().to_vec())
+	}
+}
@@ -0,0 +1,102 @@
+//! The price history kept in the offchain index.
+//!
+//! Every accepted price is written with `sp_io::offchain_index::set` by the block that
+//! accepts it, under `offchain-demo::indexing::price::` followed by the SCALE encoded asset
+//! symbol and block number. Only nodes started with `--enable-offchain-indexing true` keep it.
+//! It costs no on-chain state, so it can reach back much further than [`crate::Prices`]; the
+//! `IndexedTwap` offchain task and the `ocw_getIndexedPrices` RPC read it back.
+
+use crate::{AssetSymbol, Price};
+use parity_scale_codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::offchain::StorageKind;
+use sp_runtime::{
+	traits::{AtLeast32BitUnsigned, One},
+	RuntimeDebug,
+};
+use sp_std::prelude::*;
+
+const PRICE_KEY_PREFIX: &[u8] = b"offchain-demo::indexing::price::";
+const TWAP_KEY_PREFIX: &[u8] = b"offchain-demo::indexing::twap::";
+
+/// An accepted price and how it came about.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct IndexingData<AccountId> {
+	pub price: Price,
+	/// Milliseconds since the unix epoch.
+	pub timestamp: u64,
+	/// The oracle answers the price is the median of, empty for a forced price.
+	pub answers: Vec<(AccountId, Price)>,
+}
+
+/// The offchain storage key of the price of `asset` accepted at `block_number`.
+pub fn price_key<BlockNumber: Encode>(asset: &[u8], block_number: BlockNumber) -> Vec<u8> {
+	let mut key = PRICE_KEY_PREFIX.to_vec();
+	(asset, block_number).encode_to(&mut key);
+	key
+}
+
+/// The persistent local storage key the `IndexedTwap` offchain task caches the SCALE encoded
+/// time-weighted average [`Price`] of `asset` under.
+pub fn twap_key(asset: &[u8]) -> Vec<u8> {
+	let mut key = TWAP_KEY_PREFIX.to_vec();
+	asset.encode_to(&mut key);
+	key
+}
+
+/// Index the price of `asset` accepted at `block_number`. Called from the runtime; a later
+/// price for the same asset in the same block replaces it.
+pub(crate) fn index_price<BlockNumber: Encode, AccountId: Encode>(
+	asset: &AssetSymbol,
+	block_number: BlockNumber,
+	data: &IndexingData<AccountId>,
+) {
+	sp_io::offchain_index::set(&price_key(asset, block_number), &data.encode());
+}
+
+/// The indexed prices of `asset` accepted from block `from` to block `to`, oldest first.
+///
+/// Only callable from an offchain worker. Blocks without an indexed price are skipped.
+pub fn history<BlockNumber, AccountId>(
+	asset: &[u8],
+	from: BlockNumber,
+	to: BlockNumber,
+) -> Vec<(BlockNumber, IndexingData<AccountId>)>
+where
+	BlockNumber: AtLeast32BitUnsigned + Encode + Copy,
+	AccountId: Decode,
+{
+	read_history(asset, from, to, |key| {
+		sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, key)
+	})
+}
+
+/// Like [`history`], reading the persistent offchain storage with `read` instead, e.g. from
+/// the node.
+pub fn read_history<BlockNumber, AccountId>(
+	asset: &[u8],
+	from: BlockNumber,
+	to: BlockNumber,
+	read: impl Fn(&[u8]) -> Option<Vec<u8>>,
+) -> Vec<(BlockNumber, IndexingData<AccountId>)>
+where
+	BlockNumber: AtLeast32BitUnsigned + Encode + Copy,
+	AccountId: Decode,
+{
+	let mut prices = Vec::new();
+	let mut block_number = from;
+	while block_number <= to {
+		if let Some(data) = read(&price_key(asset, block_number))
+			.and_then(|data| IndexingData::decode(&mut &data[..]).ok())
+		{
+			prices.push((block_number, data));
+		}
+		match block_number.checked_add(&One::one()) {
+			Some(next) => block_number = next,
+			None => break,
+		}
+	}
+	prices
+}
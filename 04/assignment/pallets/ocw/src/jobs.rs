@@ -0,0 +1,142 @@
+//! Oracle jobs: arbitrary JSON endpoints registered on chain and read by the offchain worker.
+
+use crate::price::parse_signed;
+use ocw_http::json;
+use parity_scale_codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_arithmetic::FixedI128;
+use sp_runtime::RuntimeDebug;
+use sp_std::{prelude::*, str};
+
+pub type JobId = u32;
+
+/// The longest url, body, JSON pointer, header or bytes result of a job.
+pub const MAX_JOB_FIELD_LEN: usize = 1024;
+/// The most headers a job may send.
+pub const MAX_JOB_HEADERS: usize = 16;
+
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum HttpMethod {
+	Get,
+	Post,
+}
+
+/// How the value found in a job's response is read.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum ResultKind {
+	/// A JSON number or decimal string, possibly negative.
+	Number,
+	/// A JSON string as its UTF-8 bytes, any other value as its JSON text.
+	Bytes,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum JobResult {
+	Number(FixedI128),
+	Bytes(Vec<u8>),
+}
+
+/// An HTTP request whose JSON response holds a value to bring on chain.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct OracleJob {
+	pub url: Vec<u8>,
+	pub method: HttpMethod,
+	pub headers: Vec<(Vec<u8>, Vec<u8>)>,
+	/// Sent with `Post` requests only.
+	pub body: Vec<u8>,
+	/// An RFC 6901 JSON pointer such as `/rates/EUR`. Empty for the whole document.
+	pub pointer: Vec<u8>,
+	pub kind: ResultKind,
+}
+
+impl OracleJob {
+	/// Whether the job can be turned into a request: UTF-8 url, headers and pointer, and every
+	/// field within the size limits.
+	pub fn is_valid(&self) -> bool {
+		let text_ok = |bytes: &[u8]| bytes.len() <= MAX_JOB_FIELD_LEN && str::from_utf8(bytes).is_ok();
+		!self.url.is_empty() &&
+			text_ok(&self.url) &&
+			text_ok(&self.pointer) &&
+			(self.pointer.is_empty() || self.pointer.starts_with(b"/")) &&
+			self.body.len() <= MAX_JOB_FIELD_LEN &&
+			self.headers.len() <= MAX_JOB_HEADERS &&
+			self.headers.iter().all(|(name, value)| text_ok(name) && text_ok(value))
+	}
+
+	/// Read the job's result out of a response body, `None` if it is malformed or too long.
+	pub fn extract(&self, body: &[u8]) -> Option<JobResult> {
+		let value = json::pointer(body, &self.pointer)?;
+		match self.kind {
+			ResultKind::Number => {
+				let text = json::decimal_text(&value)?;
+				parse_signed(str::from_utf8(&text).ok()?).map(JobResult::Number)
+			}
+			ResultKind::Bytes => {
+				let bytes = match value {
+					serde_json::Value::String(text) => text.into_bytes(),
+					other => serde_json::to_vec(&other).ok()?,
+				};
+				(bytes.len() <= MAX_JOB_FIELD_LEN).then(|| JobResult::Bytes(bytes))
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn job(pointer: &str, kind: ResultKind) -> OracleJob {
+		OracleJob {
+			url: b"https://api.example.com/weather".to_vec(),
+			method: HttpMethod::Get,
+			headers: Vec::new(),
+			body: Vec::new(),
+			pointer: pointer.as_bytes().to_vec(),
+			kind,
+		}
+	}
+
+	const BODY: &[u8] = br#"{"city":"Oslo","temp":-3.5,"wind":{"speed":"7"}}"#;
+
+	#[test]
+	fn extracts_numbers() {
+		assert_eq!(
+			job("/temp", ResultKind::Number).extract(BODY),
+			Some(JobResult::Number(FixedI128::from_inner(-3_500_000_000_000_000_000)))
+		);
+		assert_eq!(
+			job("/wind/speed", ResultKind::Number).extract(BODY),
+			Some(JobResult::Number(FixedI128::from_inner(7_000_000_000_000_000_000)))
+		);
+		assert_eq!(job("/city", ResultKind::Number).extract(BODY), None);
+		assert_eq!(job("/missing", ResultKind::Number).extract(BODY), None);
+	}
+
+	#[test]
+	fn extracts_bytes() {
+		assert_eq!(job("/city", ResultKind::Bytes).extract(BODY), Some(JobResult::Bytes(b"Oslo".to_vec())));
+		assert_eq!(
+			job("/wind", ResultKind::Bytes).extract(BODY),
+			Some(JobResult::Bytes(br#"{"speed":"7"}"#.to_vec()))
+		);
+		assert_eq!(job("", ResultKind::Bytes).extract(b"not json"), None);
+	}
+
+	#[test]
+	fn validates_jobs() {
+		assert!(job("/temp", ResultKind::Number).is_valid());
+		assert!(job("", ResultKind::Bytes).is_valid());
+		assert!(!job("temp", ResultKind::Number).is_valid());
+		assert!(!OracleJob { url: Vec::new(), ..job("/temp", ResultKind::Number) }.is_valid());
+		assert!(!OracleJob {
+			headers: vec![(b"X-Key".to_vec(), vec![0xff])],
+			..job("/temp", ResultKind::Number)
+		}
+		.is_valid());
+	}
+}
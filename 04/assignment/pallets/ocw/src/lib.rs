@@ -1,13 +1,38 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 pub use pallet::*;
+pub use traits::{OnMissedRounds, PriceProvider};
+
+pub mod endpoints;
+pub mod indexing;
+pub mod jobs;
+pub mod migrations;
+mod price;
+pub mod ring;
+mod sources;
+mod tasks;
+pub mod traits;
+
+ocw_http::impl_host! {
+	/// The offchain host functions the worker sends its HTTP requests with.
+	pub(crate) struct OffchainHost;
+}
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
 
 #[frame_support::pallet]
 pub mod pallet {
 	//! A demonstration of an offchain worker that sends onchain callbacks
 	use core::{convert::TryInto, fmt};
 	use parity_scale_codec::{Decode, Encode};
-	use frame_support::pallet_prelude::*;
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{StorageVersion, UnixTime},
+	};
 	use frame_system::{
 		pallet_prelude::*,
 		offchain::{
@@ -16,24 +41,37 @@ pub mod pallet {
 		},
 	};
 	use sp_core::{crypto::KeyTypeId};
-	use sp_arithmetic::per_things::Permill;
+	use sp_arithmetic::{per_things::Permill, FixedU128};
 	use sp_runtime::{
 		offchain as rt_offchain,
 		traits::{
-			BlockNumberProvider
+			BlockNumberProvider, IdentifyAccount, Saturating, UniqueSaturatedInto,
 		},
 		offchain::{
 			storage::StorageValueRef,
 			storage_lock::{BlockAndTime, StorageLock},
 		},
 		transaction_validity::{
-			InvalidTransaction, TransactionSource, TransactionValidity, ValidTransaction,
+			InvalidTransaction, TransactionPriority, TransactionSource, TransactionValidity,
+			ValidTransaction,
 		},
 		RuntimeDebug,
 	};
-	use sp_std::{collections::vec_deque::VecDeque, prelude::*, str};
+	use sp_std::{prelude::*, str};
+
+	use serde::Deserialize;
 
-	use serde::{Deserialize, Deserializer};
+	use crate::OffchainHost;
+	use ocw_http::Request;
+
+	pub use crate::{
+		endpoints::Endpoint,
+		indexing::IndexingData,
+		jobs::{HttpMethod, JobId, JobResult, OracleJob, ResultKind},
+		ring::RingBuffer,
+		sources::PriceSource,
+		tasks::OffchainTask,
+	};
 
 	/// Defines application identifier for crypto keys of this module.
 	///
@@ -43,14 +81,10 @@ pub mod pallet {
 	/// `KeyTypeId` via the keystore to sign the transaction.
 	/// The keys can be inserted manually via RPC (see `author_insertKey`).
 	pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"demo");
-	const NUM_VEC_LEN: usize = 10;
+	const MAX_SYMBOL_LEN: usize = 16;
 	/// The type to sign and send transactions.
 	const UNSIGNED_TXS_PRIORITY: u64 = 100;
 
-	// We are fetching information from the github public API about organization`substrate-developer-hub`.
-	const HTTP_REMOTE_REQUEST: &str = "https://api.github.com/orgs/substrate-developer-hub";
-	const HTTP_HEADER_USER_AGENT: &str = "jimmychu0807";
-
 	const FETCH_TIMEOUT_PERIOD: u64 = 3000; // in milli-seconds
 	const LOCK_TIMEOUT_EXPIRATION: u64 = FETCH_TIMEOUT_PERIOD + 1000; // in milli-seconds
 	const LOCK_BLOCK_EXPIRATION: u32 = 3; // in block number
@@ -86,8 +120,8 @@ pub mod pallet {
 
 	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
 	pub struct Payload<Public> {
-		number: u64,
-		public: Public,
+		pub number: u64,
+		pub public: Public,
 	}
 
 	impl<T: SigningTypes> SignedPayload<T> for Payload<T::Public> {
@@ -96,67 +130,41 @@ pub mod pallet {
 		}
 	}
 
-	#[derive(Deserialize, Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
-	struct PolkadotResponse {
-		timestamp: u64,
-		data: PolkadotData
-	}
-	#[derive(Deserialize, Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
-	struct PolkadotData {
-		#[serde(deserialize_with = "de_string_to_bytes")]
-		priceUsd: Vec<u8>,
+	/// Prices reported by an oracle in an unsigned transaction, signed with the oracle's key.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub struct PricePayload<Public, BlockNumber> {
+		pub prices: Vec<(AssetSymbol, Price)>,
+		pub block_number: BlockNumber,
+		pub public: Public,
 	}
-	impl PolkadotData {
-		fn price(&self) -> Option<Price>{
-			let price_res = str::from_utf8(self.priceUsd.as_ref());
-			match price_res {
-				Ok(price) => {
-					let prices: Vec<&str> = price.split('.').collect();
-					match (prices.get(0), prices.get(1)) {
-						(Some(int_part), Some(float_part)) => {
-							match ((*int_part).parse::<u64>(), (*float_part).parse::<u32>()) {
-								(Ok(int_part), Ok(float_part)) => Some((int_part, Permill::from_parts(float_part))),
-								_ => None
-							}
-						}
-						(Some(int_part), None) => {
-							match (*int_part).parse::<u64>() {
-								Ok(int_part) => Some((int_part, Permill::from_parts(0))),
-								_ => None
-							}
-						}
-						_ => None
-					}
-				}
-				_ => None
-			}
 
+	impl<T: SigningTypes> SignedPayload<T> for PricePayload<T::Public, T::BlockNumber> {
+		fn public(&self) -> T::Public {
+			self.public.clone()
 		}
 	}
 
+	/// How the offchain worker submits prices.
+	#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub enum PriceSubmission {
+		/// A signed transaction from the oracle account, paying fees.
+		Signed,
+		/// An unsigned transaction carrying a payload signed by the oracle key. It pays no fees,
+		/// so each oracle is limited to one per `UnsignedInterval` blocks.
+		UnsignedSignedPayload,
+	}
 
 	// ref: https://serde.rs/container-attrs.html#crate
 	#[derive(Deserialize, Encode, Decode, Default)]
 	struct GithubInfo {
 		// Specify our own deserializing function to convert JSON string to vector of bytes
-		#[serde(deserialize_with = "de_string_to_bytes")]
+		#[serde(deserialize_with = "ocw_http::de_string_to_bytes")]
 		login: Vec<u8>,
-		#[serde(deserialize_with = "de_string_to_bytes")]
+		#[serde(deserialize_with = "ocw_http::de_string_to_bytes")]
 		blog: Vec<u8>,
 		public_repos: u32,
 	}
 
-	#[derive(Debug, Deserialize, Encode, Decode, Default)]
-	struct IndexingData(Vec<u8>, u64);
-
-	pub fn de_string_to_bytes<'de, D>(de: D) -> Result<Vec<u8>, D::Error>
-	where
-	D: Deserializer<'de>,
-	{
-		let s: &str = Deserialize::deserialize(de)?;
-		Ok(s.as_bytes().to_vec())
-	}
-
 	impl fmt::Debug for GithubInfo {
 		// `fmt` converts the vector of bytes inside the struct back to string for
 		//   more friendly display.
@@ -179,10 +187,82 @@ pub mod pallet {
 		type Call: From<Call<Self>>;
 		/// The identifier type for an offchain worker.
 		type AuthorityId: AppCrypto<Self::Public, Self::Signature>;
+		/// The on-chain clock, used to timestamp finalized prices.
+		type UnixTime: UnixTime;
+		/// Reports further than this from the median of all sources are dropped as outliers.
+		#[pallet::constant]
+		type MaxSourceDeviation: Get<Permill>;
+		/// The number of agreeing sources needed before a price is submitted.
+		#[pallet::constant]
+		type MinPriceSources: Get<u32>;
+		/// The origin allowed to manage the oracle set and the registered assets.
+		type OracleOrigin: EnsureOrigin<Self::Origin>;
+		/// The largest number of oracles.
+		#[pallet::constant]
+		type MaxOracles: Get<u32>;
+		/// The number of oracle answers needed to finalize a price round.
+		#[pallet::constant]
+		type OracleQuorum: Get<u32>;
+		/// The number of finalized rounds in a row an oracle may leave unanswered before
+		/// `OnMissedRounds` is called.
+		#[pallet::constant]
+		type MaxMissedRounds: Get<u32>;
+		/// Called for oracles that missed `MaxMissedRounds` rounds in a row, e.g. to slash them.
+		type OnMissedRounds: crate::OnMissedRounds<Self::AccountId>;
+		/// Finalized prices further than this from the previous accepted price are rejected.
+		#[pallet::constant]
+		type MaxPriceChange: Get<Permill>;
+		/// The number of blocks without an accepted price after which a feed is reported stale.
+		#[pallet::constant]
+		type StalePriceAge: Get<Self::BlockNumber>;
+		/// The number of numbers and of price points per asset kept in storage.
+		///
+		/// Raising it is safe: a full history grows again from its newest item. Lowering it needs
+		/// a migration: histories longer than the new length no longer decode.
+		#[pallet::constant]
+		type HistoryLength: Get<u32>;
+		/// The number of blocks of offchain indexed prices the offchain worker reads back.
+		#[pallet::constant]
+		type IndexedHistory: Get<Self::BlockNumber>;
+		/// The offchain tasks to run, each with the number of blocks between two runs.
+		///
+		/// Tasks due in the same block run in this order.
+		type OffchainTasks: Get<Vec<(OffchainTask, Self::BlockNumber)>>;
+		/// How the offchain worker submits prices.
+		type PriceSubmission: Get<PriceSubmission>;
+		/// The number of blocks an oracle waits between two unsigned price submissions.
+		#[pallet::constant]
+		type UnsignedInterval: Get<Self::BlockNumber>;
+		/// The priority of unsigned price submissions.
+		#[pallet::constant]
+		type UnsignedPriority: Get<TransactionPriority>;
+		/// Milliseconds an offchain worker run may spend on tasks. Tasks still due once it is
+		/// spent wait for the next block.
+		#[pallet::constant]
+		type OffchainTimeBudget: Get<u64>;
+		/// The number of times a request failing with a network error, 429 or 5xx is retried.
+		#[pallet::constant]
+		type HttpRetries: Get<u32>;
+		/// Milliseconds before the first retry of a request, doubled for each retry after it.
+		#[pallet::constant]
+		type HttpBackoff: Get<u64>;
+		/// The largest HTTP response body the offchain worker reads, in bytes.
+		#[pallet::constant]
+		type MaxResponseSize: Get<u32>;
+		/// The largest number of job results an oracle submits in one transaction.
+		#[pallet::constant]
+		type MaxJobResults: Get<u32>;
+		/// The largest number of price sources of an asset.
+		#[pallet::constant]
+		type MaxSources: Get<u32>;
 	}
 
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(2);
+
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	// The pallet's runtime storage items.
@@ -191,26 +271,182 @@ pub mod pallet {
 	#[pallet::getter(fn numbers)]
 	// Learn more about declaring storage items:
 	// https://substrate.dev/docs/en/knowledgebase/runtime/storage#declaring-storage-items
-	pub type Numbers<T> = StorageValue<_, VecDeque<u64>, ValueQuery>;
+	pub type Numbers<T: Config> = StorageValue<_, RingBuffer<u64, T::HistoryLength>, ValueQuery>;
+
+	/// A USD price with 18 decimals of precision.
+	pub type Price = FixedU128;
+	pub type AssetSymbol = Vec<u8>;
+	pub type RoundId = u64;
+
+	/// A finalized price and when it was finalized.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub struct PricePoint<BlockNumber> {
+		pub price: Price,
+		pub block_number: BlockNumber,
+		/// Milliseconds since the unix epoch.
+		pub timestamp: u64,
+	}
+
+	/// The assets with a price feed, and the sources each one is fetched from.
+	#[pallet::storage]
+	#[pallet::getter(fn assets)]
+	pub type Assets<T> = StorageMap<_, Blake2_128Concat, AssetSymbol, Vec<PriceSource>, OptionQuery>;
 
-	pub type Price = (u64, Permill);
+	/// The latest `HistoryLength` finalized prices of each asset.
 	#[pallet::storage]
 	#[pallet::getter(fn prices)]
-	pub type Prices<T> = StorageValue<_, VecDeque<Price>, ValueQuery>;
+	pub type Prices<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		AssetSymbol,
+		RingBuffer<PricePoint<T::BlockNumber>, T::HistoryLength>,
+		ValueQuery,
+	>;
+
+	/// The block the latest price of each asset was accepted at.
+	#[pallet::storage]
+	#[pallet::getter(fn last_accepted)]
+	pub type LastAccepted<T: Config> =
+		StorageMap<_, Blake2_128Concat, AssetSymbol, T::BlockNumber, OptionQuery>;
+
+	/// The id the next registered oracle job gets.
+	#[pallet::storage]
+	#[pallet::getter(fn next_job_id)]
+	pub type NextJobId<T> = StorageValue<_, JobId, ValueQuery>;
+
+	/// The oracle jobs run by the offchain worker.
+	#[pallet::storage]
+	#[pallet::getter(fn jobs)]
+	pub type Jobs<T> = StorageMap<_, Twox64Concat, JobId, OracleJob, OptionQuery>;
+
+	/// The latest result of each oracle job and the block it was submitted at.
+	#[pallet::storage]
+	#[pallet::getter(fn job_results)]
+	pub type JobResults<T: Config> =
+		StorageMap<_, Twox64Concat, JobId, (JobResult, T::BlockNumber), OptionQuery>;
+
+	/// The block each oracle last submitted job results at.
+	#[pallet::storage]
+	#[pallet::getter(fn last_job_results)]
+	pub type LastJobResults<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, T::BlockNumber, OptionQuery>;
+
+	/// The accounts allowed to submit prices.
+	#[pallet::storage]
+	#[pallet::getter(fn oracles)]
+	pub type Oracles<T: Config> = StorageValue<_, Vec<T::AccountId>, ValueQuery>;
+
+	/// How an oracle has kept up with the price rounds.
+	#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+	pub struct SubmissionCounters {
+		/// The number of prices the oracle submitted.
+		pub submitted: u32,
+		/// The number of rounds finalized in a row without an answer from the oracle.
+		pub missed_in_a_row: u32,
+	}
+
+	/// The submission counters of each oracle.
+	#[pallet::storage]
+	#[pallet::getter(fn oracle_counters)]
+	pub type OracleCounters<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, SubmissionCounters, ValueQuery>;
+
+	/// The first block each oracle may submit unsigned prices at again.
+	#[pallet::storage]
+	#[pallet::getter(fn next_unsigned_at)]
+	pub type NextUnsignedAt<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, T::BlockNumber, ValueQuery>;
+
+	/// The round oracle answers are currently collected for, per asset.
+	#[pallet::storage]
+	#[pallet::getter(fn current_round)]
+	pub type CurrentRound<T> = StorageMap<_, Blake2_128Concat, AssetSymbol, RoundId, ValueQuery>;
+
+	/// The answers of each oracle for a round that has not been finalized yet.
+	#[pallet::storage]
+	#[pallet::getter(fn round_answers)]
+	pub type RoundAnswers<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		AssetSymbol,
+		Twox64Concat,
+		RoundId,
+		Vec<(T::AccountId, Price)>,
+		ValueQuery,
+	>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		/// Assets registered at genesis, with their price sources.
+		pub assets: Vec<(AssetSymbol, Vec<PriceSource>)>,
+		/// The oracle set at genesis.
+		pub oracles: Vec<T::AccountId>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { assets: Vec::new(), oracles: Vec::new() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			for (asset, sources) in self.assets.iter() {
+				assert!(
+					sources.len() as u32 <= T::MaxSources::get() &&
+						sources.iter().all(PriceSource::is_valid),
+					"Invalid genesis price sources"
+				);
+				Assets::<T>::insert(asset, sources);
+			}
+			assert!(
+				self.oracles.len() as u32 <= T::MaxOracles::get(),
+				"More genesis oracles than MaxOracles"
+			);
+			Oracles::<T>::put(&self.oracles);
+		}
+	}
 
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
 		NewNumber(Option<T::AccountId>, u64),
-		NewPrice(Price),
+		/// An oracle answered a round. \[oracle, asset, round, price\]
+		PriceSubmitted(T::AccountId, AssetSymbol, RoundId, Price),
+		/// A round reached quorum and its median was added to `Prices`. \[asset, round, price\]
+		PriceFinalized(AssetSymbol, RoundId, Price),
+		/// A round's median moved more than `MaxPriceChange` from the previous price and was
+		/// dropped. \[asset, round, price, previous\]
+		PriceRejected(AssetSymbol, RoundId, Price, Price),
+		/// A price was set by `OracleOrigin`, bypassing the oracles. \[asset, price\]
+		PriceForced(AssetSymbol, Price),
+		/// No price was accepted for an asset in the last `StalePriceAge` blocks.
+		/// \[asset, last_accepted\]
+		PriceFeedStale(AssetSymbol, T::BlockNumber),
+		/// An asset was registered or had its sources replaced. \[asset\]
+		AssetRegistered(AssetSymbol),
+		/// An asset was removed from the price feeds. \[asset\]
+		AssetDeregistered(AssetSymbol),
+		/// An oracle job was registered. \[job\]
+		JobRegistered(JobId),
+		/// An oracle job was removed along with its result. \[job\]
+		JobRemoved(JobId),
+		/// An oracle submitted the result of a job. \[oracle, job, result\]
+		JobResultSubmitted(T::AccountId, JobId, JobResult),
+		/// An oracle was added to the oracle set. \[oracle\]
+		OracleAdded(T::AccountId),
+		/// An oracle was removed from the oracle set. \[oracle\]
+		OracleRemoved(T::AccountId),
+		/// An oracle missed `MaxMissedRounds` rounds in a row and `OnMissedRounds` was called.
+		/// \[oracle, missed\]
+		OracleMissedRounds(T::AccountId, u32),
 	}
 
 	// Errors inform users that something went wrong.
 	#[pallet::error]
 	pub enum Error<T> {
-		// Error returned when not sure which ocw function to executed
-		UnknownOffchainMux,
-
 		// Error returned when making signed transactions in off-chain worker
 		NoLocalAcctForSigning,
 		OffchainSignedTxError,
@@ -223,10 +459,53 @@ pub mod pallet {
 
 		// Error returned when fetching github info
 		HttpFetchingError,
+
+		// Error returned when too few price sources agree on a price
+		InsufficientPriceSources,
+
+		// Error returned when a price is submitted by an account outside the oracle set
+		NotOracle,
+		// Error returned when an oracle answers the same round twice
+		AlreadyAnswered,
+		// Errors returned when managing the oracle set
+		AlreadyOracle,
+		TooManyOracles,
+		// Errors returned when an asset is not registered or its symbol is too long
+		UnknownAsset,
+		SymbolTooLong,
+		// Errors returned when an asset has too many price sources or an invalid one
+		TooManySources,
+		InvalidSource,
+		// Errors returned when an oracle job is not registered or cannot be run
+		UnknownJob,
+		InvalidJob,
+		// Errors returned when job results are too many, repeat a job, or come a second time
+		// in a block
+		TooManyJobResults,
+		DuplicateJobResult,
+		JobResultsAlreadySubmitted,
 	}
 
 	#[pallet::hooks]
 	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_runtime_upgrade() -> Weight {
+			crate::migrations::v1::migrate::<T>().saturating_add(crate::migrations::v2::migrate::<T>())
+		}
+
+		/// Report the feeds that went stale in this block.
+		fn on_initialize(now: T::BlockNumber) -> Weight {
+			let mut reads: u64 = 0;
+			for (asset, _) in Assets::<T>::iter() {
+				reads += 2;
+				if let Some(last) = Self::last_accepted(&asset) {
+					if now == last.saturating_add(T::StalePriceAge::get()).saturating_add(1u32.into()) {
+						Self::deposit_event(Event::PriceFeedStale(asset, last));
+					}
+				}
+			}
+			T::DbWeight::get().reads(reads)
+		}
+
 		/// Offchain Worker entry point.
 		///
 		/// By implementing `fn offchain_worker` you declare a new offchain worker.
@@ -244,19 +523,26 @@ pub mod pallet {
 			// 2. Sending unsigned transaction from ocw
 			// 3. Sending unsigned transactions with signed payloads from ocw
 			// 4. Fetching JSON via http requests in ocw
-			const TX_TYPES: u32 = 5;
-			let modu = block_number.try_into().map_or(TX_TYPES, |bn: usize| (bn as u32) % TX_TYPES);
-			let result = match modu {
-				0 => Self::offchain_signed_tx(block_number),
-				1 => Self::offchain_unsigned_tx(block_number),
-				2 => Self::offchain_unsigned_tx_signed_payload(block_number),
-				3 => Self::fetch_github_info(),
-				4 => Self::fetch_price_info(block_number),
-				_ => Err(Error::<T>::UnknownOffchainMux),
-			};
+			// Each of them is a task run on its own interval, see `Config::OffchainTasks`.
+			let deadline = sp_io::offchain::timestamp()
+				.add(rt_offchain::Duration::from_millis(T::OffchainTimeBudget::get()));
+			let mut signed_tx_sent = false;
+
+			for (task, interval) in T::OffchainTasks::get() {
+				if sp_io::offchain::timestamp() >= deadline {
+					log::warn!("offchain time budget spent, {:?} waits for the next block", task);
+					break;
+				}
+				let signed = task.is_signed(T::PriceSubmission::get());
+				if signed_tx_sent && signed {
+					log::info!("{:?} waits for the next block, a signed tx was already sent", task);
+					continue;
+				}
 
-			if let Err(e) = result {
-				log::error!("offchain_worker error: {:?}", e);
+				match Self::run_task_if_due(task, interval, block_number) {
+					Ok(ran) => signed_tx_sent |= ran && signed,
+					Err(e) => log::error!("offchain_worker error in {:?}: {:?}", task, e),
+				}
 			}
 		}
 	}
@@ -288,6 +574,12 @@ pub mod pallet {
 					}
 					valid_tx(b"submit_number_unsigned_with_signed_payload".to_vec())
 				},
+				Call::submit_price_unsigned_with_signed_payload(ref payload, ref signature) => {
+					if !SignedPayload::<T>::verify::<T::AuthorityId>(payload, signature.clone()) {
+						return InvalidTransaction::BadProof.into();
+					}
+					Self::validate_price_payload(payload)
+				},
 				_ => InvalidTransaction::Call.into(),
 			}
 		}
@@ -330,79 +622,509 @@ pub mod pallet {
 			Ok(())
 		}
 
+		/// Record an oracle's answers for the current round of each asset, finalizing a round
+		///   once `OracleQuorum` oracles have answered it.
+		///
+		/// Accepted submissions are free; anyone else pays for the rejected call.
+		#[pallet::weight(10000)]
+		pub fn submit_price_signed(
+			origin: OriginFor<T>,
+			prices: Vec<(AssetSymbol, Price)>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			Self::record_prices(who, prices)?;
+			Ok(Pays::No.into())
+		}
+
+		/// Like `submit_price_signed`, for prices sent in an unsigned transaction. The payload
+		///   signature and the oracle were checked in `validate_unsigned`.
+		#[pallet::weight(10000)]
+		pub fn submit_price_unsigned_with_signed_payload(
+			origin: OriginFor<T>,
+			payload: PricePayload<T::Public, T::BlockNumber>,
+			_signature: T::Signature,
+		) -> DispatchResult {
+			ensure_none(origin)?;
+			let who = payload.public.into_account();
+			Self::record_prices(who.clone(), payload.prices)?;
+
+			let next = <frame_system::Pallet<T>>::block_number().saturating_add(T::UnsignedInterval::get());
+			NextUnsignedAt::<T>::insert(&who, next);
+			Ok(())
+		}
+
+		/// Register an asset with the sources its price is fetched from, or replace the
+		///   sources of a registered asset. Takes at most `MaxSources` sources.
+		#[pallet::weight(10000)]
+		pub fn register_asset(
+			origin: OriginFor<T>,
+			asset: AssetSymbol,
+			sources: Vec<PriceSource>,
+		) -> DispatchResult {
+			T::OracleOrigin::ensure_origin(origin)?;
+			ensure!(asset.len() <= MAX_SYMBOL_LEN, Error::<T>::SymbolTooLong);
+			ensure!(sources.len() as u32 <= T::MaxSources::get(), Error::<T>::TooManySources);
+			ensure!(sources.iter().all(PriceSource::is_valid), Error::<T>::InvalidSource);
+			Assets::<T>::insert(&asset, sources);
+			Self::deposit_event(Event::AssetRegistered(asset));
+			Ok(())
+		}
+
+		/// Set the price of an asset, bypassing the oracles and the `MaxPriceChange` check.
+		///
+		/// Meant to resume a feed whose rounds keep being rejected after a genuine market move.
+		#[pallet::weight(10000)]
+		pub fn force_price(origin: OriginFor<T>, asset: AssetSymbol, price: Price) -> DispatchResult {
+			T::OracleOrigin::ensure_origin(origin)?;
+			ensure!(Assets::<T>::contains_key(&asset), Error::<T>::UnknownAsset);
+			Self::append_or_replace_price(&asset, price, &[]);
+			Self::deposit_event(Event::PriceForced(asset, price));
+			Ok(())
+		}
+
+		/// Stop the price feed of an asset, dropping its unfinished rounds. Its price history is kept.
+		#[pallet::weight(10000)]
+		pub fn deregister_asset(origin: OriginFor<T>, asset: AssetSymbol) -> DispatchResult {
+			T::OracleOrigin::ensure_origin(origin)?;
+			ensure!(Assets::<T>::contains_key(&asset), Error::<T>::UnknownAsset);
+			Assets::<T>::remove(&asset);
+			CurrentRound::<T>::remove(&asset);
+			let _ = RoundAnswers::<T>::remove_prefix(&asset, None);
+			Self::deposit_event(Event::AssetDeregistered(asset));
+			Ok(())
+		}
+
+		/// Register an oracle job for the offchain worker to run.
+		#[pallet::weight(10000)]
+		pub fn register_job(origin: OriginFor<T>, job: OracleJob) -> DispatchResult {
+			T::OracleOrigin::ensure_origin(origin)?;
+			ensure!(job.is_valid(), Error::<T>::InvalidJob);
+			let id = Self::next_job_id();
+			Jobs::<T>::insert(id, job);
+			NextJobId::<T>::put(id.wrapping_add(1));
+			Self::deposit_event(Event::JobRegistered(id));
+			Ok(())
+		}
+
 		#[pallet::weight(10000)]
-		pub fn submit_price_signed(origin: OriginFor<T>, price: Price) -> DispatchResult {
+		pub fn remove_job(origin: OriginFor<T>, id: JobId) -> DispatchResult {
+			T::OracleOrigin::ensure_origin(origin)?;
+			ensure!(Jobs::<T>::contains_key(id), Error::<T>::UnknownJob);
+			Jobs::<T>::remove(id);
+			JobResults::<T>::remove(id);
+			Self::deposit_event(Event::JobRemoved(id));
+			Ok(())
+		}
+
+		/// Record an oracle's results for some jobs, replacing the previous results.
+		///
+		/// Takes at most `MaxJobResults` results, one per job. Each oracle may submit once per
+		///   block; accepted submissions are free.
+		#[pallet::weight(
+			T::DbWeight::get().reads_writes(2, 1).saturating_add(10000).saturating_add(
+				T::DbWeight::get().reads_writes(1, 1).saturating_mul(results.len() as Weight)
+			)
+		)]
+		pub fn submit_job_results(
+			origin: OriginFor<T>,
+			results: Vec<(JobId, JobResult)>,
+		) -> DispatchResultWithPostInfo {
 			let who = ensure_signed(origin)?;
-			Self::append_or_replace_price(price);
-			Self::deposit_event(Event::NewPrice(price));
+			ensure!(Self::oracles().contains(&who), Error::<T>::NotOracle);
+			ensure!(results.len() as u32 <= T::MaxJobResults::get(), Error::<T>::TooManyJobResults);
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(Self::last_job_results(&who) != Some(now), Error::<T>::JobResultsAlreadySubmitted);
+			for (index, (id, _)) in results.iter().enumerate() {
+				ensure!(Jobs::<T>::contains_key(id), Error::<T>::UnknownJob);
+				ensure!(
+					results[..index].iter().all(|(earlier, _)| earlier != id),
+					Error::<T>::DuplicateJobResult
+				);
+			}
+
+			LastJobResults::<T>::insert(&who, now);
+			for (id, result) in results {
+				JobResults::<T>::insert(id, (result.clone(), now));
+				Self::deposit_event(Event::JobResultSubmitted(who.clone(), id, result));
+			}
+			Ok(Pays::No.into())
+		}
+
+		#[pallet::weight(10000)]
+		pub fn add_oracle(origin: OriginFor<T>, oracle: T::AccountId) -> DispatchResult {
+			T::OracleOrigin::ensure_origin(origin)?;
+			Oracles::<T>::try_mutate(|oracles| -> DispatchResult {
+				ensure!(!oracles.contains(&oracle), Error::<T>::AlreadyOracle);
+				ensure!((oracles.len() as u32) < T::MaxOracles::get(), Error::<T>::TooManyOracles);
+				oracles.push(oracle.clone());
+				Ok(())
+			})?;
+			Self::deposit_event(Event::OracleAdded(oracle));
+			Ok(())
+		}
+
+		#[pallet::weight(10000)]
+		pub fn remove_oracle(origin: OriginFor<T>, oracle: T::AccountId) -> DispatchResult {
+			T::OracleOrigin::ensure_origin(origin)?;
+			Oracles::<T>::try_mutate(|oracles| -> DispatchResult {
+				let index = oracles.iter().position(|o| o == &oracle).ok_or(Error::<T>::NotOracle)?;
+				oracles.swap_remove(index);
+				Ok(())
+			})?;
+			OracleCounters::<T>::remove(&oracle);
+			LastJobResults::<T>::remove(&oracle);
+			Self::deposit_event(Event::OracleRemoved(oracle));
 			Ok(())
 		}
 	}
 
 	impl<T: Config> Pallet<T> {
-		/// Append a new number to the tail of the list, removing an element from the head if reaching
-		///   the bounded length.
+		/// Append a new number to the history, overwriting the oldest one once `HistoryLength`
+		///   numbers are kept.
 		fn append_or_replace_number(number: u64) {
 			Numbers::<T>::mutate(|numbers| {
-				if numbers.len() == NUM_VEC_LEN {
-					let _ = numbers.pop_front();
-				}
-				numbers.push_back(number);
-				log::info!("Number vector: {:?}", numbers);
+				numbers.push(number);
+				log::info!("Number history: {:?}", numbers);
 			});
 		}
 
-		fn append_or_replace_price(price: Price) {
-			Prices::<T>::mutate(|prices| {
-				if prices.len() == 10 {
-					let _ = prices.pop_front();
+		/// Record an oracle's answers for the current round of each asset, finalizing a round
+		///   once `OracleQuorum` oracles have answered it.
+		fn record_prices(who: T::AccountId, prices: Vec<(AssetSymbol, Price)>) -> DispatchResult {
+			ensure!(Self::oracles().contains(&who), Error::<T>::NotOracle);
+
+			// Check the whole batch before recording any of it.
+			for (index, (asset, _)) in prices.iter().enumerate() {
+				ensure!(Assets::<T>::contains_key(asset), Error::<T>::UnknownAsset);
+				let round = Self::current_round(asset);
+				ensure!(
+					prices[..index].iter().all(|(earlier, _)| earlier != asset) &&
+						Self::round_answers(asset, round).iter().all(|(oracle, _)| oracle != &who),
+					Error::<T>::AlreadyAnswered
+				);
+			}
+
+			for (asset, price) in prices {
+				let round = Self::current_round(&asset);
+				let answers = RoundAnswers::<T>::mutate(&asset, round, |answers| {
+					answers.push((who.clone(), price));
+					answers.clone()
+				});
+				OracleCounters::<T>::mutate(&who, |counters| {
+					counters.submitted = counters.submitted.saturating_add(1);
+					counters.missed_in_a_row = 0;
+				});
+				Self::deposit_event(Event::PriceSubmitted(who.clone(), asset.clone(), round, price));
+
+				if answers.len() as u32 >= T::OracleQuorum::get() {
+					Self::finalize_round(asset, round, &answers);
 				}
-				prices.push_back(price);
-			});
+			}
+			Ok(())
 		}
 
-		fn fetch_price_info(block_number: T::BlockNumber) -> Result<(), Error<T>> {
-			// TODO: 这是你们的功课
+		/// Accept a price payload from an oracle at most once per `UnsignedInterval` blocks.
+		fn validate_price_payload(payload: &PricePayload<T::Public, T::BlockNumber>) -> TransactionValidity {
+			let who = payload.public.clone().into_account();
+			if !Self::oracles().contains(&who) {
+				return InvalidTransaction::BadSigner.into();
+			}
+			if payload.block_number < Self::next_unsigned_at(&who) {
+				return InvalidTransaction::Stale.into();
+			}
+			if payload.block_number > <frame_system::Pallet<T>>::block_number() {
+				return InvalidTransaction::Future.into();
+			}
+
+			ValidTransaction::with_tag_prefix("ocw-demo-price")
+				.priority(T::UnsignedPriority::get())
+				.and_provides((payload.block_number, payload.public.clone()))
+				.longevity(T::UnsignedInterval::get().unique_saturated_into())
+				.propagate(true)
+				.build()
+		}
+
+		/// Store the median of a round's answers and open the asset's next round.
+		///
+		/// A median further than `MaxPriceChange` from the previous price trips the circuit
+		///   breaker: it is dropped and the feed goes stale until the oracles agree with the
+		///   previous price again or `force_price` is called.
+		fn finalize_round(asset: AssetSymbol, round: RoundId, answers: &[(T::AccountId, Price)]) {
+			let prices: Vec<Price> = answers.iter().map(|(_, price)| *price).collect();
+			if let Some(price) = crate::price::median_price(&prices) {
+				let previous = Self::prices(&asset).last().map(|point| point.price);
+				match previous {
+					Some(previous) if !crate::price::within_change(previous, price, T::MaxPriceChange::get()) =>
+						Self::deposit_event(Event::PriceRejected(asset.clone(), round, price, previous)),
+					_ => {
+						Self::append_or_replace_price(&asset, price, answers);
+						Self::deposit_event(Event::PriceFinalized(asset.clone(), round, price));
+					}
+				}
+			}
+			RoundAnswers::<T>::remove(&asset, round);
+			CurrentRound::<T>::insert(&asset, round.saturating_add(1));
+			Self::count_missed_round(answers);
+		}
+
+		/// Count a finalized round against every oracle that did not answer it, calling
+		///   `OnMissedRounds` for the ones reaching `MaxMissedRounds` in a row.
+		fn count_missed_round(answers: &[(T::AccountId, Price)]) {
+			for oracle in Self::oracles() {
+				if answers.iter().any(|(answered, _)| answered == &oracle) {
+					continue;
+				}
+				let missed = OracleCounters::<T>::mutate(&oracle, |counters| {
+					counters.missed_in_a_row = counters.missed_in_a_row.saturating_add(1);
+					if counters.missed_in_a_row < T::MaxMissedRounds::get() {
+						return None;
+					}
+					Some(sp_std::mem::take(&mut counters.missed_in_a_row))
+				});
+				if let Some(missed) = missed {
+					T::OnMissedRounds::on_missed_rounds(&oracle, missed);
+					Self::deposit_event(Event::OracleMissedRounds(oracle, missed));
+				}
+			}
+		}
+
+		/// Store an accepted price and write it, with the answers it came from, to the offchain
+		///   index.
+		fn append_or_replace_price(asset: &AssetSymbol, price: Price, answers: &[(T::AccountId, Price)]) {
+			let block_number = <frame_system::Pallet<T>>::block_number();
+			let timestamp = T::UnixTime::now().as_millis() as u64;
+			let point = PricePoint { price, block_number, timestamp };
+			crate::indexing::index_price(
+				asset,
+				block_number,
+				&IndexingData { price, timestamp, answers: answers.to_vec() },
+			);
+			LastAccepted::<T>::insert(asset, block_number);
+			Prices::<T>::mutate(asset, |prices| prices.push(point));
+		}
+
+		/// The time-weighted average price of `asset` over the prices accepted in the last
+		///   `IndexedHistory` blocks before `block_number`, read from the offchain index.
+		///
+		/// Only callable from an offchain worker, and `None` unless the node indexes. It reads up
+		///   to `IndexedHistory` local storage entries, so it runs in the `IndexedTwap` task on its
+		///   own interval rather than in the price feed.
+		pub fn indexed_twap(asset: &AssetSymbol, block_number: T::BlockNumber) -> Option<Price> {
+			let from = block_number.saturating_sub(T::IndexedHistory::get());
+			let points: Vec<(u64, Price)> =
+				crate::indexing::history::<T::BlockNumber, T::AccountId>(asset, from, block_number)
+					.into_iter()
+					.map(|(_, data)| (data.timestamp, data.price))
+					.collect();
+			let start = points.first()?.0;
+			let now = sp_io::offchain::timestamp().unix_millis();
+			crate::price::twap(&points, now, now.saturating_sub(start))
+		}
+
+		/// The prices of `asset` finalized in the last `window` milliseconds, oldest first.
+		fn prices_within(asset: &AssetSymbol, window: u64) -> Vec<Price> {
+			let start = (T::UnixTime::now().as_millis() as u64).saturating_sub(window);
+			Prices::<T>::get(asset)
+				.iter()
+				.filter(|point| point.timestamp >= start)
+				.map(|point| point.price)
+				.collect()
+		}
 
+		fn fetch_price_info(block_number: T::BlockNumber) -> Result<(), Error<T>> {
 			// 利用 offchain worker 取出 DOT 当前对 USD 的价格，并把写到一个 Vec 的存储里，
 			// 你们自己选一种方法提交回链上，并在代码注释为什么用这种方法提交回链上最好。只保留当前最近的 10 个价格，
 			// 其他价格可丢弃 （就是 Vec 的长度长到 10 后，这时再插入一个值时，要先丢弃最早的那个值）。
 
 			// 取得的价格 parse 完后，放在以下存儲：
-			// pub type Prices<T> = StorageValue<_, VecDeque<(u64, Permill)>, ValueQuery>
+			// pub type Prices<T> = StorageMap<_, Blake2_128Concat, AssetSymbol, RingBuffer<PricePoint<T::BlockNumber>, T::HistoryLength>, ValueQuery>
+
+			// 每个已注册资产的价格来源由 `register_asset` 配置，多个来源取中位数并剔除离群值。
+			let prices: Vec<(AssetSymbol, Price)> = Assets::<T>::iter()
+				.filter_map(|(asset, sources)| match Self::fetch_aggregated_price(&sources) {
+					Ok(price) => {
+						log::info!("price of {:?}: {:?}", asset, price);
+						Some((asset, price))
+					}
+					Err(e) => {
+						log::warn!("no price for asset {:?}: {:?}", asset, e);
+						None
+					}
+				})
+				.collect();
+			if prices.is_empty() {
+				return Err(<Error<T>>::InsufficientPriceSources);
+			}
 
-			// 这个 http 请求可得到当前 DOT 价格：
-			// [https://api.coincap.io/v2/assets/polkadot](https://api.coincap.io/v2/assets/polkadot)。
-			let url = "https://api.coincap.io/v2/assets/polkadot";
-			let resp_bytes = Self::fetch_from_url(url)?;
-			let resp_str = str::from_utf8(&resp_bytes).map_err(|_| <Error<T>>::HttpFetchingError)?;
-			let polkdot_resp: PolkadotResponse = serde_json::from_str(resp_str).map_err(|_| <Error<T>>::HttpFetchingError)?;
-			let price = polkdot_resp.data.price();
-
-			if let Some(price) = price {
-				// 使用无签名交易提交到链，原因是使得dot价格具备最高的可信度
-				let signer = Signer::<T, T::AuthorityId>::any_account();
-				let result = signer.send_signed_transaction(|_acct|
-					// This is the on-chain function
-					Call::submit_price_signed(price)
+			// 提交方式由 `Config::PriceSubmission` 选择：签名交易要付手续费，带签名负载的无签名交易
+			// 不付手续费，由 `validate_unsigned` 校验签名和预言机身份，并限制每个预言机的提交频率。
+			match T::PriceSubmission::get() {
+				PriceSubmission::Signed => Self::submit_prices_signed(prices),
+				PriceSubmission::UnsignedSignedPayload => Self::submit_prices_unsigned(prices, block_number),
+			}
+		}
+
+		fn submit_prices_signed(prices: Vec<(AssetSymbol, Price)>) -> Result<(), Error<T>> {
+			let signer = Signer::<T, T::AuthorityId>::any_account();
+			let result = signer.send_signed_transaction(|_acct|
+				// This is the on-chain function
+				Call::submit_price_signed(prices.clone())
+			);
+			// Display error if the signed tx fails.
+			if let Some((acc, res)) = result {
+				if res.is_err() {
+					log::error!("failure: offchain_signed_tx: tx sent: {:?}", acc.id);
+					return Err(<Error<T>>::OffchainSignedTxError);
+				}
+				// Transaction is sent successfully
+				return Ok(());
+			}
+
+			// The case of `None`: no account is available for sending
+			log::error!("No local account available");
+			Err(<Error<T>>::NoLocalAcctForSigning)
+		}
+
+		fn submit_prices_unsigned(
+			prices: Vec<(AssetSymbol, Price)>,
+			block_number: T::BlockNumber,
+		) -> Result<(), Error<T>> {
+			let signer = Signer::<T, T::AuthorityId>::any_account();
+			if let Some((_, res)) = signer.send_unsigned_transaction(
+				|acct| PricePayload { prices: prices.clone(), block_number, public: acct.public.clone() },
+				Call::submit_price_unsigned_with_signed_payload
+				) {
+				return res.map_err(|_| {
+					log::error!("Failed in submit_prices_unsigned");
+					<Error<T>>::OffchainUnsignedTxSignedPayloadError
+				});
+			}
+
+			// The case of `None`: no account is available for sending
+			log::error!("No local account available");
+			Err(<Error<T>>::NoLocalAcctForSigning)
+		}
+
+		/// Query every price source of an asset at once and combine the answers.
+		fn fetch_aggregated_price(sources: &[PriceSource]) -> Result<Price, Error<T>> {
+			let user_agent = Endpoint::UserAgent.get();
+			let user_agent = str::from_utf8(&user_agent).map_err(|_| <Error<T>>::HttpFetchingError)?;
+
+			let mut sent = Vec::new();
+			let mut requests = Vec::new();
+			for source in sources.iter() {
+				let url = source.url();
+				let url = match str::from_utf8(&url) {
+					Ok(url) => url,
+					Err(_) => {
+						log::warn!("skipping price source with invalid url: {:?}", source);
+						continue;
+					}
+				};
+				log::info!("sending request to: {}", url);
+				sent.push(source);
+				requests.push(
+					Request::get(url)
+						.header("User-Agent", user_agent)
+						.timeout(FETCH_TIMEOUT_PERIOD)
+						.max_body(T::MaxResponseSize::get()),
 				);
-				// Display error if the signed tx fails.
-				if let Some((acc, res)) = result {
-					if res.is_err() {
-						log::error!("failure: offchain_signed_tx: tx sent: {:?}", acc.id);
-						return Err(<Error<T>>::OffchainSignedTxError);
+			}
+
+			// All requests are sent first, then waited for together.
+			let responses = ocw_http::send_all::<OffchainHost>(&requests);
+			let prices: Vec<Price> = sent
+				.into_iter()
+				.zip(responses)
+				.filter_map(|(source, response)| {
+					let response = match response {
+						Ok(response) => response,
+						Err(e) => {
+							log::warn!("price source {:?} failed: {:?}", source, e);
+							return None;
+						}
+					};
+					let price = source.extract(&response.body);
+					if price.is_none() {
+						log::warn!("price source {:?} returned a malformed response", source);
 					}
-					// Transaction is sent successfully
-					return Ok(());
+					price
+				})
+				.collect();
+
+			crate::price::aggregate(&prices, T::MaxSourceDeviation::get(), T::MinPriceSources::get())
+				.ok_or(<Error<T>>::InsufficientPriceSources)
+		}
+
+		/// Run every registered oracle job at once and submit the results in one transaction.
+		fn run_oracle_jobs() -> Result<(), Error<T>> {
+			let jobs: Vec<(JobId, OracleJob)> = Jobs::<T>::iter().collect();
+			if jobs.is_empty() {
+				return Ok(());
+			}
+			let mut sent = Vec::new();
+			let mut requests = Vec::new();
+			for (id, job) in jobs.iter() {
+				match Self::job_request(job) {
+					Some(request) => {
+						sent.push((*id, job));
+						requests.push(request);
+					}
+					None => log::warn!("failed to build request for oracle job {}", id),
 				}
-				Err(Error::<T>::HttpFetchingError)
 			}
-			else {
-				Err(Error::<T>::HttpFetchingError)
+
+			let responses = ocw_http::send_all::<OffchainHost>(&requests);
+			let results: Vec<(JobId, JobResult)> = sent
+				.into_iter()
+				.zip(responses)
+				.filter_map(|((id, job), response)| match response {
+					Ok(response) => {
+						let result = job.extract(&response.body);
+						if result.is_none() {
+							log::warn!("oracle job {} returned a malformed response", id);
+						}
+						result.map(|result| (id, result))
+					}
+					Err(e) => {
+						log::warn!("no response for oracle job {}: {:?}", id, e);
+						None
+					}
+				})
+				.take(T::MaxJobResults::get() as usize)
+				.collect();
+			if results.is_empty() {
+				return Err(<Error<T>>::HttpFetchingError);
 			}
 
+			let signer = Signer::<T, T::AuthorityId>::any_account();
+			match signer.send_signed_transaction(|_acct| Call::submit_job_results(results.clone())) {
+				Some((_, Ok(()))) => Ok(()),
+				Some((acc, Err(()))) => {
+					log::error!("failure: run_oracle_jobs: tx sent: {:?}", acc.id);
+					Err(<Error<T>>::OffchainSignedTxError)
+				}
+				None => {
+					log::error!("No local account available");
+					Err(<Error<T>>::NoLocalAcctForSigning)
+				}
+			}
 		}
 
+		/// The request of `job`, `None` if its url or a header is not UTF-8.
+		fn job_request(job: &OracleJob) -> Option<Request> {
+			let url = str::from_utf8(&job.url).ok()?;
+			let mut request = match job.method {
+				HttpMethod::Get => Request::get(url),
+				HttpMethod::Post => Request::post(url, job.body.clone()),
+			}
+			.timeout(FETCH_TIMEOUT_PERIOD)
+			.max_body(T::MaxResponseSize::get());
+			for (name, value) in job.headers.iter() {
+				request = request.header(str::from_utf8(name).ok()?, str::from_utf8(value).ok()?);
+			}
+			Some(request)
+		}
 
 		/// Check if we have fetched github info before. If yes, we can use the cached version
 		///   stored in off-chain worker storage `storage`. If not, we fetch the remote info and
@@ -465,51 +1187,94 @@ pub mod pallet {
 
 			// Deserializing JSON to struct, thanks to `serde` and `serde_derive`
 			let gh_info: GithubInfo =
-			serde_json::from_str(resp_str).map_err(|_| <Error<T>>::HttpFetchingError)?;
+			ocw_http::json::decode(resp_str.as_bytes()).map_err(|_| <Error<T>>::HttpFetchingError)?;
 			Ok(gh_info)
 		}
 
 		/// This function uses the `offchain::http` API to query the remote github information,
 		///   and returns the JSON response as vector of bytes.
 		fn fetch_from_remote() -> Result<Vec<u8>, Error<T>> {
-			 Self::fetch_from_url(HTTP_REMOTE_REQUEST)
+			let url = Endpoint::GithubUrl.get();
+			Self::fetch_from_url(str::from_utf8(&url).map_err(|_| <Error<T>>::HttpFetchingError)?)
 		}
 
-
+		/// GET `url`, retrying transient failures within `FETCH_TIMEOUT_PERIOD` and answering
+		///   from the `ETag` cache when the remote has not changed.
 		fn fetch_from_url(url: &str) -> Result<Vec<u8>, Error<T>> {
 			log::info!("sending request to: {}", url);
-
-			// Initiate an external HTTP GET request. This is using high-level wrappers from `sp_runtime`.
-			let request = rt_offchain::http::Request::get(url);
-
-			// Keeping the offchain worker execution time reasonable, so limiting the call to be within 3s.
-			let timeout = sp_io::offchain::timestamp()
-				.add(rt_offchain::Duration::from_millis(FETCH_TIMEOUT_PERIOD));
+			let user_agent = Endpoint::UserAgent.get();
+			let user_agent = str::from_utf8(&user_agent).map_err(|_| <Error<T>>::HttpFetchingError)?;
 
 			// For github API request, we also need to specify `user-agent` in http request header.
 			//   See: https://developer.github.com/v3/#user-agent-required
-			let pending = request
-				.add_header("User-Agent", HTTP_HEADER_USER_AGENT)
-				.deadline(timeout) // Setting the timeout time
-				.send() // Sending the request out by the host
-				.map_err(|_| <Error<T>>::HttpFetchingError)?;
-
-			// By default, the http request is async from the runtime perspective. So we are asking the
-			//   runtime to wait here.
-			// The returning value here is a `Result` of `Result`, so we are unwrapping it twice by two `?`
-			//   ref: https://substrate.dev/rustdocs/v2.0.0/sp_runtime/offchain/http/struct.PendingRequest.html#method.try_wait
-			let response = pending
-				.try_wait(timeout)
-				.map_err(|_| <Error<T>>::HttpFetchingError)?
-				.map_err(|_| <Error<T>>::HttpFetchingError)?;
-
-			if response.code != 200 {
-				log::error!("Unexpected http request status code: {}", response.code);
-				return Err(<Error<T>>::HttpFetchingError);
+			// Keeping the offchain worker execution time reasonable, so limiting the call, retries
+			//   included, to be within 3s.
+			Request::get(url)
+				.header("User-Agent", user_agent)
+				.timeout(FETCH_TIMEOUT_PERIOD)
+				.retries(T::HttpRetries::get(), T::HttpBackoff::get())
+				.max_body(T::MaxResponseSize::get())
+				.cache_etag()
+				.send::<OffchainHost>()
+				.map(|response| response.body)
+				.map_err(|e| {
+					log::error!("request to {} failed: {:?}", url, e);
+					<Error<T>>::HttpFetchingError
+				})
+		}
+
+		/// Run `task` if `interval` blocks have passed since its last run. Returns whether it ran.
+		///
+		/// The last run is recorded in local storage before the task starts, so a failing task is
+		/// retried on its next interval rather than on every block.
+		fn run_task_if_due(
+			task: OffchainTask,
+			interval: T::BlockNumber,
+			block_number: T::BlockNumber,
+		) -> Result<bool, Error<T>> {
+			let lock_key = task.lock_key();
+			let mut lock = StorageLock::<BlockAndTime<Self>>::with_block_and_time_deadline(
+				&lock_key, LOCK_BLOCK_EXPIRATION,
+				rt_offchain::Duration::from_millis(LOCK_TIMEOUT_EXPIRATION)
+				);
+			// Another run of the worker, e.g. for a sibling block, is still busy with this task.
+			let _guard = match lock.try_lock() {
+				Ok(guard) => guard,
+				Err(_) => return Ok(false),
+			};
+
+			let last_run_key = task.last_run_key();
+			let last_run = StorageValueRef::persistent(&last_run_key);
+			if let Ok(Some(last)) = last_run.get::<T::BlockNumber>() {
+				if block_number < last.saturating_add(interval) {
+					return Ok(false);
+				}
 			}
+			last_run.set(&block_number);
+
+			match task {
+				OffchainTask::SignedTx => Self::offchain_signed_tx(block_number),
+				OffchainTask::UnsignedTx => Self::offchain_unsigned_tx(block_number),
+				OffchainTask::UnsignedTxSignedPayload =>
+					Self::offchain_unsigned_tx_signed_payload(block_number),
+				OffchainTask::GithubInfo => Self::fetch_github_info(),
+				OffchainTask::PriceFeed => Self::fetch_price_info(block_number),
+				OffchainTask::OracleJobs => Self::run_oracle_jobs(),
+				OffchainTask::IndexedTwap => Self::cache_indexed_twaps(block_number),
+			}
+			.map(|_| true)
+		}
 
-			// Next we fully read the response body and collect it to a vector of bytes.
-			Ok(response.body().collect::<Vec<u8>>())
+		/// Cache the indexed time-weighted average price of every registered asset in local
+		///   storage. Assets without indexed prices keep their previous value.
+		fn cache_indexed_twaps(block_number: T::BlockNumber) -> Result<(), Error<T>> {
+			for (asset, _) in Assets::<T>::iter() {
+				if let Some(twap) = Self::indexed_twap(&asset, block_number) {
+					log::info!("indexed twap of {:?}: {:?}", asset, twap);
+					StorageValueRef::persistent(&crate::indexing::twap_key(&asset)).set(&twap);
+				}
+			}
+			Ok(())
 		}
 
 		fn offchain_signed_tx(block_number: T::BlockNumber) -> Result<(), Error<T>> {
@@ -585,6 +1350,36 @@ pub mod pallet {
 		}
 	}
 
+	impl<T: Config> crate::PriceProvider<T::BlockNumber> for Pallet<T> {
+		fn latest(asset: &AssetSymbol) -> Option<Price> {
+			Prices::<T>::get(asset).last().map(|point| point.price)
+		}
+
+		fn twap(asset: &AssetSymbol, window: u64) -> Option<Price> {
+			let points: Vec<(u64, Price)> =
+				Prices::<T>::get(asset).iter().map(|point| (point.timestamp, point.price)).collect();
+			crate::price::twap(&points, T::UnixTime::now().as_millis() as u64, window)
+		}
+
+		fn min(asset: &AssetSymbol, window: u64) -> Option<Price> {
+			Self::prices_within(asset, window).into_iter().min()
+		}
+
+		fn max(asset: &AssetSymbol, window: u64) -> Option<Price> {
+			Self::prices_within(asset, window).into_iter().max()
+		}
+
+		fn mean(asset: &AssetSymbol, window: u64) -> Option<Price> {
+			crate::price::mean(&Self::prices_within(asset, window))
+		}
+
+		fn is_stale(asset: &AssetSymbol, max_age: T::BlockNumber) -> bool {
+			Self::last_accepted(asset).map_or(true, |last| {
+				<frame_system::Pallet<T>>::block_number() > last.saturating_add(max_age)
+			})
+		}
+	}
+
 	impl<T: Config> BlockNumberProvider for Pallet<T> {
 		type BlockNumber = T::BlockNumber;
 
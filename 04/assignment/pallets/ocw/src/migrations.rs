@@ -0,0 +1,153 @@
+//! Storage migrations for pallet-ocw.
+
+/// From the single DOT feed to per-asset feeds: the `Prices` history moves from a plain
+/// value into the `Prices` map under [`DEFAULT_ASSET`](v0::DEFAULT_ASSET). Run as part of
+/// [`v1::migrate`].
+pub mod v0 {
+	use super::v1::{OldPrice, OldPricePoint};
+	use crate::{Config, Pallet};
+	use frame_support::{
+		storage::migration::{get_storage_value, put_storage_value, take_storage_value},
+		traits::{Get, GetStorageVersion, PalletInfoAccess, UnixTime},
+		weights::Weight,
+		Blake2_128Concat, StorageHasher,
+	};
+	use parity_scale_codec::Encode;
+	use sp_std::prelude::*;
+
+	/// The asset the pallet fetched prices of before it kept a feed per asset.
+	pub const DEFAULT_ASSET: &[u8] = b"DOT";
+
+	/// Move the single price history into the `Prices` map under [`DEFAULT_ASSET`], in front
+	/// of any prices the asset already has there.
+	///
+	/// The old prices carry no block number or time, so they are stamped with the current
+	/// ones. Does nothing if the on-chain storage version is already 1 or higher, or if there
+	/// is no single price history.
+	pub fn migrate<T: Config>() -> Weight {
+		if Pallet::<T>::on_chain_storage_version() >= 1 {
+			return 0;
+		}
+
+		let pallet = <Pallet<T> as PalletInfoAccess>::name().as_bytes();
+		// A `VecDeque` encodes like a `Vec`.
+		let old: Vec<OldPrice> = match take_storage_value(pallet, b"Prices", &[]) {
+			Some(old) => old,
+			None => return T::DbWeight::get().reads(1),
+		};
+		let block_number = frame_system::Pallet::<T>::block_number();
+		let timestamp = T::UnixTime::now().as_millis() as u64;
+		let mut points: Vec<OldPricePoint<T::BlockNumber>> = old
+			.into_iter()
+			.map(|price| OldPricePoint { price, block_number, timestamp })
+			.collect();
+
+		let key = Blake2_128Concat::hash(&DEFAULT_ASSET.to_vec().encode());
+		let newer: Option<Vec<OldPricePoint<T::BlockNumber>>> =
+			get_storage_value(pallet, b"Prices", &key);
+		points.extend(newer.unwrap_or_default());
+		put_storage_value(pallet, b"Prices", &key, points);
+
+		T::DbWeight::get().reads_writes(2, 2)
+	}
+}
+
+/// Version 1: prices are stored as `FixedU128` instead of `(u64, Permill)`.
+pub mod v1 {
+	use crate::{AssetSymbol, Config, Pallet, Price, PricePoint, RoundAnswers};
+	use frame_support::{
+		storage::migration::{put_storage_value, storage_key_iter},
+		traits::{Get, GetStorageVersion, PalletInfoAccess, StorageVersion},
+		weights::Weight,
+		Blake2_128Concat, StorageHasher,
+	};
+	use parity_scale_codec::{Decode, Encode};
+	use sp_arithmetic::{per_things::Permill, FixedPointNumber};
+	use sp_std::prelude::*;
+
+	pub(super) type OldPrice = (u64, Permill);
+
+	#[derive(Encode, Decode)]
+	pub(super) struct OldPricePoint<BlockNumber> {
+		pub(super) price: OldPrice,
+		pub(super) block_number: BlockNumber,
+		pub(super) timestamp: u64,
+	}
+
+	fn convert((integer, fraction): OldPrice) -> Price {
+		// A millionth is 10^12 in the 18 decimals of `Price`; neither term can overflow.
+		Price::from_inner(
+			integer as u128 * Price::DIV + fraction.deconstruct() as u128 * 1_000_000_000_000,
+		)
+	}
+
+	/// Re-encode every stored price and bump the storage version to 1.
+	///
+	/// The single price history of a chain older than the per-asset feeds is moved into the
+	/// map with [`v0::migrate`](super::v0::migrate) first. Does nothing if the on-chain
+	/// storage version is already 1 or higher.
+	pub fn migrate<T: Config>() -> Weight {
+		if Pallet::<T>::on_chain_storage_version() >= 1 {
+			return 0;
+		}
+
+		let moved = super::v0::migrate::<T>();
+		let mut translated: u64 = 0;
+		// `Prices` has changed layout again since, so it is written as the plain vector of
+		// version 1 for `v2` to pick up.
+		let pallet = <Pallet<T> as PalletInfoAccess>::name().as_bytes();
+		let old: Vec<(AssetSymbol, Vec<OldPricePoint<T::BlockNumber>>)> =
+			storage_key_iter::<_, _, Blake2_128Concat>(pallet, b"Prices").collect();
+		for (asset, old) in old {
+			translated += 1;
+			let points: Vec<PricePoint<T::BlockNumber>> = old
+				.into_iter()
+				.map(|point| PricePoint {
+					price: convert(point.price),
+					block_number: point.block_number,
+					timestamp: point.timestamp,
+				})
+				.collect();
+			put_storage_value(pallet, b"Prices", &Blake2_128Concat::hash(&asset.encode()), points);
+		}
+		RoundAnswers::<T>::translate::<Vec<(T::AccountId, OldPrice)>, _>(|_, _, old| {
+			translated += 1;
+			Some(old.into_iter().map(|(oracle, price)| (oracle, convert(price))).collect())
+		});
+
+		StorageVersion::new(1).put::<Pallet<T>>();
+
+		T::DbWeight::get().reads_writes(translated + 1, translated + 1).saturating_add(moved)
+	}
+}
+
+/// Version 2: `Numbers` and `Prices` are ring buffers of `Config::HistoryLength` items.
+pub mod v2 {
+	use crate::{Config, Numbers, Pallet, PricePoint, Prices, RingBuffer};
+	use frame_support::{
+		traits::{Get, GetStorageVersion, StorageVersion},
+		weights::Weight,
+	};
+	use sp_std::prelude::*;
+
+	/// Move the number and price histories into ring buffers and bump the storage version to 2.
+	///
+	/// Only the newest `HistoryLength` items of each history are kept. Does nothing if the
+	/// on-chain storage version is already 2 or higher.
+	pub fn migrate<T: Config>() -> Weight {
+		if Pallet::<T>::on_chain_storage_version() >= 2 {
+			return 0;
+		}
+
+		let mut translated: u64 = 1;
+		let _ = Numbers::<T>::translate::<Vec<u64>, _>(|old| old.map(RingBuffer::from_oldest));
+		Prices::<T>::translate::<Vec<PricePoint<T::BlockNumber>>, _>(|_, old| {
+			translated += 1;
+			Some(RingBuffer::from_oldest(old))
+		});
+
+		StorageVersion::new(2).put::<Pallet<T>>();
+
+		T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+	}
+}
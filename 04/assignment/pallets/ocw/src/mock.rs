@@ -0,0 +1,409 @@
+use crate as pallet_ocw;
+use crate::{OffchainTask, PriceSource, PriceSubmission};
+use frame_support::{parameter_types, traits::{GenesisBuild, Get, UnixTime}};
+use frame_system as system;
+use parking_lot::RwLock;
+use sp_core::{
+	offchain::{
+		testing::{self, OffchainState, PoolState, TestOffchainExt, TestTransactionPoolExt},
+		Externalities, HttpError, HttpRequestId, HttpRequestStatus, OffchainDbExt,
+		OffchainWorkerExt, OpaqueNetworkState, OpaquePeerId, Timestamp, TransactionPoolExt,
+	},
+	sr25519::{self, Signature},
+	H256,
+};
+use sp_keystore::{testing::KeyStore, KeystoreExt, SyncCryptoStore};
+use sp_runtime::{
+	testing::{Header, TestXt},
+	traits::{BlakeTwo256, Extrinsic as ExtrinsicT, IdentifyAccount, IdentityLookup, Verify},
+	Permill, RuntimeAppPublic,
+};
+use std::{cell::RefCell, collections::BTreeMap, sync::Arc, time::Duration};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::AccountId;
+pub type Extrinsic = TestXt<Call, ()>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		OcwDemo: pallet_ocw::{Pallet, Call, Storage, Event<T>, Config<T>, ValidateUnsigned},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const SS58Prefix: u8 = 42;
+}
+
+impl system::Config for Test {
+	type BaseCallFilter = frame_support::traits::AllowAll;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = SS58Prefix;
+	type OnSetCode = ();
+}
+
+impl system::offchain::SigningTypes for Test {
+	type Public = <Signature as Verify>::Signer;
+	type Signature = Signature;
+}
+
+impl<LocalCall> system::offchain::SendTransactionTypes<LocalCall> for Test
+where
+	Call: From<LocalCall>,
+{
+	type OverarchingCall = Call;
+	type Extrinsic = Extrinsic;
+}
+
+impl<LocalCall> system::offchain::CreateSignedTransaction<LocalCall> for Test
+where
+	Call: From<LocalCall>,
+{
+	fn create_transaction<C: system::offchain::AppCrypto<Self::Public, Self::Signature>>(
+		call: Call,
+		_public: <Signature as Verify>::Signer,
+		_account: AccountId,
+		nonce: u64,
+	) -> Option<(Call, <Extrinsic as ExtrinsicT>::SignaturePayload)> {
+		Some((call, (nonce, ())))
+	}
+}
+
+thread_local! {
+	static OFFCHAIN_TASKS: RefCell<Vec<(OffchainTask, u64)>> = RefCell::new(Vec::new());
+	static NOW: RefCell<u64> = RefCell::new(0);
+	static PRICE_SUBMISSION: RefCell<PriceSubmission> = RefCell::new(PriceSubmission::Signed);
+	static ORACLE_QUORUM: RefCell<u32> = RefCell::new(1);
+	static MISSED_ROUNDS: RefCell<Vec<(AccountId, u32)>> = RefCell::new(Vec::new());
+}
+
+/// The task registry, set per test with [`set_offchain_tasks`].
+pub struct OffchainTasks;
+impl Get<Vec<(OffchainTask, u64)>> for OffchainTasks {
+	fn get() -> Vec<(OffchainTask, u64)> {
+		OFFCHAIN_TASKS.with(|tasks| tasks.borrow().clone())
+	}
+}
+
+pub fn set_offchain_tasks(tasks: Vec<(OffchainTask, u64)>) {
+	OFFCHAIN_TASKS.with(|t| *t.borrow_mut() = tasks);
+}
+
+/// How prices are submitted, set per test with [`set_price_submission`].
+pub struct MockPriceSubmission;
+impl Get<PriceSubmission> for MockPriceSubmission {
+	fn get() -> PriceSubmission {
+		PRICE_SUBMISSION.with(|submission| *submission.borrow())
+	}
+}
+
+pub fn set_price_submission(submission: PriceSubmission) {
+	PRICE_SUBMISSION.with(|s| *s.borrow_mut() = submission);
+}
+
+/// The number of answers finalizing a round, 1 unless set per test with [`set_oracle_quorum`].
+pub struct MockOracleQuorum;
+impl Get<u32> for MockOracleQuorum {
+	fn get() -> u32 {
+		ORACLE_QUORUM.with(|quorum| *quorum.borrow())
+	}
+}
+
+pub fn set_oracle_quorum(quorum: u32) {
+	ORACLE_QUORUM.with(|q| *q.borrow_mut() = quorum);
+}
+
+/// The on-chain clock, set per test with [`set_now`].
+pub struct MockTime;
+impl UnixTime for MockTime {
+	fn now() -> Duration {
+		Duration::from_millis(NOW.with(|now| *now.borrow()))
+	}
+}
+
+pub fn set_now(millis: u64) {
+	NOW.with(|now| *now.borrow_mut() = millis);
+}
+
+/// Records the oracles reported to `OnMissedRounds`, read with [`missed_rounds`].
+pub struct RecordMissedRounds;
+impl pallet_ocw::OnMissedRounds<AccountId> for RecordMissedRounds {
+	fn on_missed_rounds(oracle: &AccountId, missed: u32) {
+		MISSED_ROUNDS.with(|reported| reported.borrow_mut().push((*oracle, missed)));
+	}
+}
+
+pub fn missed_rounds() -> Vec<(AccountId, u32)> {
+	MISSED_ROUNDS.with(|reported| reported.borrow().clone())
+}
+
+parameter_types! {
+	pub const MaxSourceDeviation: Permill = Permill::from_percent(5);
+	pub const MinPriceSources: u32 = 1;
+	pub const MaxOracles: u32 = 4;
+	pub const MaxMissedRounds: u32 = 2;
+	pub const MaxPriceChange: Permill = Permill::from_percent(20);
+	pub const StalePriceAge: u64 = 10;
+	pub const HistoryLength: u32 = 3;
+	pub const IndexedHistory: u64 = 20;
+	pub const UnsignedInterval: u64 = 5;
+	pub const UnsignedPriority: u64 = 1 << 20;
+	pub const OffchainTimeBudget: u64 = 5_000;
+	pub const HttpRetries: u32 = 2;
+	pub const HttpBackoff: u64 = 100;
+	pub const MaxResponseSize: u32 = 1024;
+	pub const MaxJobResults: u32 = 2;
+	pub const MaxSources: u32 = 3;
+}
+
+impl pallet_ocw::Config for Test {
+	type Event = Event;
+	type Call = Call;
+	type AuthorityId = pallet_ocw::crypto::TestAuthId;
+	type UnixTime = MockTime;
+	type MaxSourceDeviation = MaxSourceDeviation;
+	type MinPriceSources = MinPriceSources;
+	type OracleOrigin = system::EnsureRoot<AccountId>;
+	type MaxOracles = MaxOracles;
+	type OracleQuorum = MockOracleQuorum;
+	type MaxMissedRounds = MaxMissedRounds;
+	type OnMissedRounds = RecordMissedRounds;
+	type MaxPriceChange = MaxPriceChange;
+	type StalePriceAge = StalePriceAge;
+	type HistoryLength = HistoryLength;
+	type IndexedHistory = IndexedHistory;
+	type OffchainTasks = OffchainTasks;
+	type PriceSubmission = MockPriceSubmission;
+	type UnsignedInterval = UnsignedInterval;
+	type UnsignedPriority = UnsignedPriority;
+	type OffchainTimeBudget = OffchainTimeBudget;
+	type HttpRetries = HttpRetries;
+	type HttpBackoff = HttpBackoff;
+	type MaxResponseSize = MaxResponseSize;
+	type MaxJobResults = MaxJobResults;
+	type MaxSources = MaxSources;
+}
+
+pub const COINCAP_URL: &str = "https://api.coincap.io/v2/assets/polkadot";
+pub const CRYPTOCOMPARE_URL: &str = "https://min-api.cryptocompare.com/data/price?fsym=DOT&tsyms=USD";
+pub const GITHUB_URL: &str = "https://api.github.com/orgs/substrate-developer-hub";
+
+/// Statuses forced onto HTTP requests, by url, and whether they only apply to the next request.
+pub type HttpFaults = Arc<RwLock<BTreeMap<String, (HttpRequestStatus, bool)>>>;
+
+/// `TestOffchainExt` answers every expected request with status 200. This wraps it so a test
+/// can make the requests to some urls return another status or time out.
+pub struct FaultyOffchainExt {
+	inner: TestOffchainExt,
+	state: Arc<RwLock<OffchainState>>,
+	faults: HttpFaults,
+}
+
+impl Externalities for FaultyOffchainExt {
+	fn is_validator(&self) -> bool {
+		self.inner.is_validator()
+	}
+
+	fn network_state(&self) -> Result<OpaqueNetworkState, ()> {
+		self.inner.network_state()
+	}
+
+	fn timestamp(&mut self) -> Timestamp {
+		self.inner.timestamp()
+	}
+
+	fn sleep_until(&mut self, deadline: Timestamp) {
+		self.inner.sleep_until(deadline)
+	}
+
+	fn random_seed(&mut self) -> [u8; 32] {
+		self.inner.random_seed()
+	}
+
+	fn http_request_start(&mut self, method: &str, uri: &str, meta: &[u8]) -> Result<HttpRequestId, ()> {
+		self.inner.http_request_start(method, uri, meta)
+	}
+
+	fn http_request_add_header(&mut self, request_id: HttpRequestId, name: &str, value: &str) -> Result<(), ()> {
+		self.inner.http_request_add_header(request_id, name, value)
+	}
+
+	fn http_request_write_body(
+		&mut self,
+		request_id: HttpRequestId,
+		chunk: &[u8],
+		deadline: Option<Timestamp>,
+	) -> Result<(), HttpError> {
+		self.inner.http_request_write_body(request_id, chunk, deadline)
+	}
+
+	fn http_response_wait(&mut self, ids: &[HttpRequestId], deadline: Option<Timestamp>) -> Vec<HttpRequestStatus> {
+		ids.iter()
+			.map(|id| {
+				let uri = self.state.read().requests.get(id).map(|request| request.uri.clone());
+				let fault = uri.and_then(|uri| {
+					let mut faults = self.faults.write();
+					let (status, once) = faults.get(&uri).cloned()?;
+					if once {
+						faults.remove(&uri);
+					}
+					Some(status)
+				});
+				match fault {
+					Some(status) => status,
+					None => self.inner.http_response_wait(&[*id], deadline).remove(0),
+				}
+			})
+			.collect()
+	}
+
+	fn http_response_headers(&mut self, request_id: HttpRequestId) -> Vec<(Vec<u8>, Vec<u8>)> {
+		self.inner.http_response_headers(request_id)
+	}
+
+	fn http_response_read_body(
+		&mut self,
+		request_id: HttpRequestId,
+		buffer: &mut [u8],
+		deadline: Option<Timestamp>,
+	) -> Result<usize, HttpError> {
+		self.inner.http_response_read_body(request_id, buffer, deadline)
+	}
+
+	fn set_authorized_nodes(&mut self, nodes: Vec<OpaquePeerId>, authorized_only: bool) {
+		self.inner.set_authorized_nodes(nodes, authorized_only)
+	}
+}
+
+/// Handles to inspect and steer the offchain environment of a test.
+pub struct OffchainHandles {
+	pub state: Arc<RwLock<OffchainState>>,
+	pub pool: Arc<RwLock<PoolState>>,
+	pub faults: HttpFaults,
+	/// The key in the keystore, which is also the only oracle.
+	pub public: sr25519::Public,
+}
+
+impl OffchainHandles {
+	/// Answer a GET request to `url` with `body`.
+	pub fn expect_get(&self, url: &str, body: &[u8]) {
+		self.state.write().expect_request(testing::PendingRequest {
+			method: "GET".into(),
+			uri: url.into(),
+			headers: vec![("User-Agent".into(), "jimmychu0807".into())],
+			response: Some(body.to_vec()),
+			sent: true,
+			..Default::default()
+		});
+	}
+
+	/// Make requests to `url` finish with `status` instead.
+	pub fn fail(&self, url: &str, status: HttpRequestStatus) {
+		self.faults.write().insert(url.into(), (status, false));
+	}
+
+	/// Make the next request to `url` finish with `status` instead.
+	pub fn fail_once(&self, url: &str, status: HttpRequestStatus) {
+		self.faults.write().insert(url.into(), (status, true));
+	}
+
+	/// Decode the transactions submitted to the pool so far, oldest first.
+	pub fn take_transactions(&self) -> Vec<Extrinsic> {
+		use parity_scale_codec::Decode;
+		self.pool
+			.write()
+			.transactions
+			.drain(..)
+			.map(|tx| Extrinsic::decode(&mut &*tx).unwrap())
+			.collect()
+	}
+}
+
+const PHRASE: &str = "news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut storage = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	GenesisBuild::<Test>::assimilate_storage(
+		&pallet_ocw::GenesisConfig::<Test> {
+			assets: vec![(
+				b"DOT".to_vec(),
+				vec![
+					PriceSource::CoinCap { asset_id: b"polkadot".to_vec() },
+					PriceSource::CryptoCompare { symbol: b"DOT".to_vec() },
+				],
+			)],
+			oracles: Vec::new(),
+		},
+		&mut storage,
+	)
+	.unwrap();
+
+	let mut ext = sp_io::TestExternalities::new(storage);
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
+
+/// Like [`new_test_ext`], with the offchain worker, transaction pool and keystore extensions
+/// registered and the keystore key made an oracle.
+pub fn new_offchain_test_ext() -> (sp_io::TestExternalities, OffchainHandles) {
+	let keystore = KeyStore::new();
+	let public = SyncCryptoStore::sr25519_generate_new(
+		&keystore,
+		pallet_ocw::crypto::Public::ID,
+		Some(&format!("{}/hunter1", PHRASE)),
+	)
+	.unwrap();
+
+	let mut ext = new_test_ext();
+	// Share the offchain database so prices indexed by blocks are visible to the offchain worker
+	// once `persist_offchain_overlay` is called.
+	let (offchain, state) = TestOffchainExt::with_offchain_db(ext.offchain_db());
+	let (pool, pool_state) = TestTransactionPoolExt::new();
+	let faults = HttpFaults::default();
+	ext.register_extension(OffchainDbExt::new(offchain.clone()));
+	ext.register_extension(OffchainWorkerExt::new(FaultyOffchainExt {
+		inner: offchain,
+		state: state.clone(),
+		faults: faults.clone(),
+	}));
+	ext.register_extension(TransactionPoolExt::new(pool));
+	ext.register_extension(KeystoreExt(Arc::new(keystore)));
+	ext.execute_with(|| pallet_ocw::Oracles::<Test>::put(vec![public]));
+
+	(ext, OffchainHandles { state, pool: pool_state, faults, public })
+}
+
+/// Run the offchain worker at `block` with `task` as the only registered task.
+pub fn run_offchain_task(task: OffchainTask, block: u64) {
+	use frame_support::traits::Hooks;
+	set_offchain_tasks(vec![(task, 1)]);
+	System::set_block_number(block);
+	OcwDemo::offchain_worker(block);
+}
@@ -0,0 +1,246 @@
+//! Parsing and aggregation of prices reported by the price sources.
+
+use crate::Price;
+use ocw_http::number::{parse_decimal, parse_signed_decimal};
+use sp_arithmetic::{
+	per_things::Permill,
+	traits::{CheckedDiv, Saturating, Zero},
+	FixedI128, FixedPointNumber,
+};
+use sp_std::prelude::*;
+
+/// Decimal digits of precision of [`Price`].
+const PRICE_DECIMALS: u32 = 18;
+
+/// Parse a decimal string into a [`Price`].
+///
+/// Accepts an optional leading `+`, an integer part, a fractional part or both (`"6"`,
+/// `"6.5"`, `".5"`, `"6."`), and an optional exponent (`"1.5e-7"`, `"2E+3"`). Digits beyond
+/// the 18 decimals of precision are truncated. Returns `None` for negative, empty or
+/// malformed input and for values that do not fit.
+pub fn parse_price(price: &str) -> Option<Price> {
+	parse_decimal(price, PRICE_DECIMALS).map(Price::from_inner)
+}
+
+/// Parse a decimal string that may be negative, such as a temperature, with the same rules as
+/// [`parse_price`].
+pub fn parse_signed(value: &str) -> Option<FixedI128> {
+	parse_signed_decimal(value, PRICE_DECIMALS).map(FixedI128::from_inner)
+}
+
+/// Median of `values`, averaging the two middle values for an even count.
+fn median(values: &mut Vec<u128>) -> Option<u128> {
+	if values.is_empty() {
+		return None;
+	}
+	values.sort_unstable();
+	let mid = values.len() / 2;
+	if values.len() % 2 == 0 {
+		let (a, b) = (values[mid - 1], values[mid]);
+		Some(a / 2 + b / 2 + (a % 2 + b % 2) / 2)
+	} else {
+		Some(values[mid])
+	}
+}
+
+/// The median of `prices`, `None` if there are none.
+pub fn median_price(prices: &[Price]) -> Option<Price> {
+	let mut all: Vec<u128> = prices.iter().map(|price| price.into_inner()).collect();
+	median(&mut all).map(Price::from_inner)
+}
+
+/// Combine the prices reported by several sources into one.
+///
+/// Prices further than `max_deviation` from the median of all reports are dropped as
+/// outliers and the median of the rest is returned. Returns `None` when fewer than
+/// `min_sources` reports survive, so a single misbehaving source cannot move the result.
+pub fn aggregate(prices: &[Price], max_deviation: Permill, min_sources: u32) -> Option<Price> {
+	let mut all: Vec<u128> = prices.iter().map(|price| price.into_inner()).collect();
+	let median_all = median(&mut all)?;
+	let tolerance = max_deviation * median_all;
+
+	let mut accepted: Vec<u128> = all
+		.into_iter()
+		.filter(|price| {
+			let deviation = if *price > median_all { price - median_all } else { median_all - price };
+			deviation <= tolerance
+		})
+		.collect();
+	if (accepted.len() as u32) < min_sources.max(1) {
+		return None;
+	}
+	median(&mut accepted).map(Price::from_inner)
+}
+
+/// The arithmetic mean of `prices`, `None` if there are none.
+pub fn mean(prices: &[Price]) -> Option<Price> {
+	let sum = prices.iter().fold(Price::zero(), |sum, price| sum.saturating_add(*price));
+	sum.checked_div(&Price::saturating_from_integer(prices.len() as u64))
+}
+
+/// Whether `price` is within `max_change` of `previous`.
+pub fn within_change(previous: Price, price: Price, max_change: Permill) -> bool {
+	let (previous, price) = (previous.into_inner(), price.into_inner());
+	let change = if price > previous { price - previous } else { previous - price };
+	change <= max_change * previous
+}
+
+/// The time-weighted average of `points` over the `window` milliseconds before `now`.
+///
+/// `points` are `(timestamp, price)` pairs, oldest first. Each price counts for as long as it
+/// was the latest one, so a price set before the window starts still counts from its start.
+/// Returns the latest price when the window is empty, and `None` when there are no prices.
+pub fn twap(points: &[(u64, Price)], now: u64, window: u64) -> Option<Price> {
+	let start = now.saturating_sub(window);
+	let mut weighted = Price::zero();
+	let mut total: u64 = 0;
+	for (index, (timestamp, price)) in points.iter().enumerate() {
+		let from = (*timestamp).max(start);
+		let until = points.get(index + 1).map_or(now, |(next, _)| *next).min(now);
+		if until <= from {
+			continue;
+		}
+		let duration = until - from;
+		weighted = weighted.saturating_add(price.saturating_mul(Price::saturating_from_integer(duration)));
+		total = total.saturating_add(duration);
+	}
+
+	if total == 0 {
+		return points.last().map(|(_, price)| *price);
+	}
+	weighted.checked_div(&Price::saturating_from_integer(total))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn price(inner: u128) -> Option<Price> {
+		Some(Price::from_inner(inner))
+	}
+
+	const UNIT: u128 = 1_000_000_000_000_000_000;
+
+	#[test]
+	fn parses_plain_decimals() {
+		assert_eq!(parse_price("6"), price(6 * UNIT));
+		assert_eq!(parse_price("6.5"), price(6 * UNIT + UNIT / 2));
+		assert_eq!(parse_price("6.05"), price(6 * UNIT + UNIT / 20));
+		assert_eq!(parse_price("6.000005"), price(6 * UNIT + 5_000_000_000_000));
+		assert_eq!(parse_price("0.000000000000000001"), price(1));
+		assert_eq!(parse_price("0"), price(0));
+		assert_eq!(parse_price("007.50"), price(7 * UNIT + UNIT / 2));
+		assert_eq!(parse_price("+6.5"), price(6 * UNIT + UNIT / 2));
+		assert_eq!(parse_price(" 6.5 "), price(6 * UNIT + UNIT / 2));
+	}
+
+	#[test]
+	fn parses_missing_integer_or_fraction() {
+		assert_eq!(parse_price(".5"), price(UNIT / 2));
+		assert_eq!(parse_price("6."), price(6 * UNIT));
+	}
+
+	#[test]
+	fn truncates_digits_beyond_precision() {
+		assert_eq!(parse_price("1.0000000000000000019"), price(UNIT + 1));
+		assert_eq!(parse_price("0.0000000000000000009"), price(0));
+		assert_eq!(
+			parse_price("28.123456789012345678901234567890123456789"),
+			price(28 * UNIT + 123_456_789_012_345_678)
+		);
+	}
+
+	#[test]
+	fn parses_exponent_notation() {
+		assert_eq!(parse_price("1.5e-7"), price(150_000_000_000));
+		assert_eq!(parse_price("1.5E-7"), price(150_000_000_000));
+		assert_eq!(parse_price("2e3"), price(2_000 * UNIT));
+		assert_eq!(parse_price("2E+3"), price(2_000 * UNIT));
+		assert_eq!(parse_price("65e-1"), price(6 * UNIT + UNIT / 2));
+		assert_eq!(parse_price(".5e1"), price(5 * UNIT));
+		assert_eq!(parse_price("1e-18"), price(1));
+		assert_eq!(parse_price("1e-19"), price(0));
+		assert_eq!(parse_price("1e-400"), price(0));
+	}
+
+	#[test]
+	fn rejects_malformed_input() {
+		for input in [
+			"", ".", "+", "-6.5", "6.5.1", "6,5", "abc", "6.5a", "e5", "1e", "1e+", "1e-", "1e1.5",
+			"1e99999999999", "NaN", "inf", "0x10",
+		]
+		.iter()
+		{
+			assert_eq!(parse_price(input), None, "{:?} should be rejected", input);
+		}
+	}
+
+	#[test]
+	fn rejects_values_that_do_not_fit() {
+		// FixedU128 holds up to about 3.4e20.
+		assert_eq!(parse_price("1e21"), None);
+		assert_eq!(parse_price("340282366920938463464"), None);
+		assert!(parse_price("340282366920938463463").is_some());
+	}
+
+	#[test]
+	fn aggregate_rejects_outliers() {
+		let prices: Vec<Price> = ["10", "10.2", "9.9", "1000"]
+			.iter()
+			.map(|p| parse_price(p).unwrap())
+			.collect();
+		assert_eq!(aggregate(&prices, Permill::from_percent(5), 1), parse_price("10"));
+		assert_eq!(aggregate(&prices, Permill::from_percent(5), 4), None);
+	}
+
+	#[test]
+	fn median_averages_the_middle_pair() {
+		let prices: Vec<Price> = ["1", "3", "2", "4"].iter().map(|p| parse_price(p).unwrap()).collect();
+		assert_eq!(median_price(&prices), parse_price("2.5"));
+		assert_eq!(median_price(&[]), None);
+	}
+
+	#[test]
+	fn parses_signed_decimals() {
+		assert_eq!(parse_signed("-2.5"), Some(FixedI128::from_inner(-2_500_000_000_000_000_000)));
+		assert_eq!(parse_signed("2.5"), Some(FixedI128::from_inner(2_500_000_000_000_000_000)));
+		assert_eq!(parse_signed(" -0.1e1 "), Some(FixedI128::from_inner(-1_000_000_000_000_000_000)));
+		assert_eq!(parse_signed("--1"), None);
+		assert_eq!(parse_signed("340282366920938463463"), None);
+	}
+
+	#[test]
+	fn mean_of_prices() {
+		let prices: Vec<Price> = ["1", "2", "4"].iter().map(|p| parse_price(p).unwrap()).collect();
+		assert_eq!(mean(&prices), parse_price("2.333333333333333333"));
+		assert_eq!(mean(&[]), None);
+	}
+
+	#[test]
+	fn within_change_bounds_both_directions() {
+		let previous = parse_price("10").unwrap();
+		let max_change = Permill::from_percent(10);
+		assert!(within_change(previous, parse_price("11").unwrap(), max_change));
+		assert!(within_change(previous, parse_price("9").unwrap(), max_change));
+		assert!(!within_change(previous, parse_price("11.01").unwrap(), max_change));
+		assert!(!within_change(previous, parse_price("8.99").unwrap(), max_change));
+	}
+
+	#[test]
+	fn twap_weights_prices_by_duration() {
+		let points = [(1_000, parse_price("10").unwrap()), (4_000, parse_price("20").unwrap())];
+		// 10 for 3s and 20 for 1s.
+		assert_eq!(twap(&points, 5_000, 4_000), parse_price("12.5"));
+		// The window starts at 2s: 10 for 2s and 20 for 1s.
+		assert_eq!(twap(&points, 5_000, 3_000), parse_price("13.333333333333333333"));
+		// Only the latest price falls inside the window.
+		assert_eq!(twap(&points, 5_000, 500), parse_price("20"));
+	}
+
+	#[test]
+	fn twap_of_an_empty_window_is_the_latest_price() {
+		let points = [(1_000, parse_price("10").unwrap()), (4_000, parse_price("20").unwrap())];
+		assert_eq!(twap(&points, 4_000, 0), parse_price("20"));
+		assert_eq!(twap(&[], 4_000, 1_000), None);
+	}
+}
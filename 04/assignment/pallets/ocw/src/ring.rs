@@ -0,0 +1,168 @@
+//! A fixed capacity history that overwrites its oldest item once full.
+
+use frame_support::{storage::bounded_vec::BoundedVec, traits::Get};
+use parity_scale_codec::{Decode, Encode, Input};
+use sp_std::{convert::TryFrom, fmt, prelude::*};
+
+/// The last `S` items pushed, kept in a [`BoundedVec`].
+///
+/// Until the buffer is full items are appended. After that a push overwrites the oldest item
+/// in place and moves `head` on, so it never shifts the other items.
+#[derive(Encode)]
+pub struct RingBuffer<T, S> {
+	items: BoundedVec<T, S>,
+	/// The index of the oldest item. Always 0 until the buffer is full.
+	head: u32,
+}
+
+impl<T, S: Get<u32>> RingBuffer<T, S> {
+	/// Build a buffer from `items`, oldest first, keeping only the newest `S` of them.
+	pub fn from_oldest(mut items: Vec<T>) -> Self {
+		let excess = items.len().saturating_sub(S::get() as usize);
+		let items = BoundedVec::try_from(items.split_off(excess)).unwrap_or_default();
+		Self { items, head: 0 }
+	}
+
+	/// Add `item` as the newest item, overwriting the oldest one if the buffer is full.
+	pub fn push(&mut self, item: T) {
+		let len = self.items.len();
+		if len < S::get() as usize {
+			if self.head != 0 {
+				// `S` grew after a wrap: put the items back in order before appending.
+				let mut items = sp_std::mem::take(&mut self.items).into_inner();
+				items.rotate_left(self.head as usize);
+				self.items = BoundedVec::try_from(items).unwrap_or_default();
+				self.head = 0;
+			}
+			let _ = self.items.try_push(item);
+		} else if let Some(oldest) = self.items.get_mut(self.head as usize) {
+			*oldest = item;
+			self.head = ((self.head as usize + 1) % len) as u32;
+		}
+	}
+
+	/// The newest item.
+	pub fn last(&self) -> Option<&T> {
+		match self.head {
+			0 => self.items.last(),
+			head => self.items.get(head as usize - 1),
+		}
+	}
+
+	/// The items, oldest first.
+	pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+		let (newer, older) = self.items.split_at(self.head as usize);
+		older.iter().chain(newer.iter())
+	}
+
+	pub fn len(&self) -> usize {
+		self.items.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.items.is_empty()
+	}
+}
+
+impl<T: Decode, S: Get<u32>> Decode for RingBuffer<T, S> {
+	fn decode<I: Input>(input: &mut I) -> Result<Self, parity_scale_codec::Error> {
+		let items = BoundedVec::<T, S>::decode(input)?;
+		let head = u32::decode(input)?;
+		if head as usize >= items.len().max(1) {
+			return Err("RingBuffer head out of bounds".into());
+		}
+		Ok(Self { items, head })
+	}
+}
+
+impl<T, S> Default for RingBuffer<T, S> {
+	fn default() -> Self {
+		Self { items: Default::default(), head: 0 }
+	}
+}
+
+impl<T: Clone, S> Clone for RingBuffer<T, S> {
+	fn clone(&self) -> Self {
+		Self { items: self.items.clone(), head: self.head }
+	}
+}
+
+impl<T: PartialEq, S> PartialEq for RingBuffer<T, S> {
+	fn eq(&self, other: &Self) -> bool {
+		self.items == other.items && self.head == other.head
+	}
+}
+
+impl<T: Eq, S> Eq for RingBuffer<T, S> {}
+
+impl<T: fmt::Debug, S> fmt::Debug for RingBuffer<T, S> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RingBuffer").field("items", &*self.items).field("head", &self.head).finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frame_support::parameter_types;
+
+	parameter_types! {
+		pub const Three: u32 = 3;
+		pub static Length: u32 = 3;
+	}
+
+	fn items(buffer: &RingBuffer<u32, Three>) -> Vec<u32> {
+		buffer.iter().copied().collect()
+	}
+
+	#[test]
+	fn overwrites_the_oldest_item_once_full() {
+		let mut buffer = RingBuffer::<u32, Three>::default();
+		assert_eq!(buffer.last(), None);
+		for item in 1..=3 {
+			buffer.push(item);
+		}
+		assert_eq!(items(&buffer), vec![1, 2, 3]);
+		assert_eq!(buffer.last(), Some(&3));
+
+		buffer.push(4);
+		buffer.push(5);
+		assert_eq!(items(&buffer), vec![3, 4, 5]);
+		assert_eq!(buffer.last(), Some(&5));
+		assert_eq!(buffer.len(), 3);
+	}
+
+	#[test]
+	fn stays_in_order_when_the_length_grows_after_a_wrap() {
+		let mut buffer = RingBuffer::<u32, Length>::from_oldest(vec![1, 2, 3]);
+		buffer.push(4);
+		buffer.push(5);
+
+		Length::set(4);
+		buffer.push(6);
+		assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), vec![3, 4, 5, 6]);
+		assert_eq!(buffer.last(), Some(&6));
+
+		buffer.push(7);
+		assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), vec![4, 5, 6, 7]);
+	}
+
+	#[test]
+	fn keeps_the_newest_items() {
+		let buffer = RingBuffer::<u32, Three>::from_oldest(vec![1, 2, 3, 4, 5]);
+		assert_eq!(items(&buffer), vec![3, 4, 5]);
+	}
+
+	#[test]
+	fn round_trips_through_scale() {
+		let mut buffer = RingBuffer::<u32, Three>::from_oldest(vec![1, 2, 3]);
+		buffer.push(4);
+		let decoded = RingBuffer::<u32, Three>::decode(&mut &buffer.encode()[..]).unwrap();
+		assert_eq!(decoded, buffer);
+		assert_eq!(items(&decoded), vec![2, 3, 4]);
+
+		// A head past the end of the items is rejected.
+		let corrupt = (vec![1u32, 2], 2u32).encode();
+		assert!(RingBuffer::<u32, Three>::decode(&mut &corrupt[..]).is_err());
+	}
+}
@@ -0,0 +1,75 @@
+//! The HTTP endpoints the offchain worker reads prices from.
+
+use crate::{price::parse_price, Endpoint, Price};
+use ocw_http::json;
+use parity_scale_codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::RuntimeDebug;
+use sp_std::{prelude::*, str};
+
+/// The longest asset id, symbol, url or pointer of a price source, in bytes.
+pub const MAX_SOURCE_FIELD_LEN: usize = 1024;
+
+/// A price source and the way its response is read.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum PriceSource {
+	/// The coincap v2 asset API for the coincap asset id, e.g. `polkadot`, reading `data.priceUsd`.
+	CoinCap { asset_id: Vec<u8> },
+	/// The cryptocompare price API for a ticker symbol, e.g. `DOT`, reading the `USD` field.
+	CryptoCompare { symbol: Vec<u8> },
+	/// Any JSON endpoint, with the price found at an RFC 6901 JSON pointer such as
+	/// `/data/price`. The value may be a JSON string or number.
+	JsonPath { url: Vec<u8>, pointer: Vec<u8> },
+}
+
+impl PriceSource {
+	/// The URL to send a GET request to. Only callable from an offchain worker, as the coincap
+	/// and cryptocompare base URLs come from [`Endpoint`].
+	pub fn url(&self) -> Vec<u8> {
+		match self {
+			PriceSource::CoinCap { asset_id } =>
+				[&Endpoint::CoinCapUrl.get()[..], &asset_id[..]].concat(),
+			PriceSource::CryptoCompare { symbol } => [
+				&Endpoint::CryptoCompareUrl.get()[..],
+				&b"?fsym="[..],
+				&symbol[..],
+				&b"&tsyms=USD"[..],
+			]
+			.concat(),
+			PriceSource::JsonPath { url, .. } => url.clone(),
+		}
+	}
+
+	/// Whether the source can be turned into a request: non-empty UTF-8 fields within
+	/// [`MAX_SOURCE_FIELD_LEN`], and a JSON pointer starting with `/`.
+	pub fn is_valid(&self) -> bool {
+		let text_ok = |bytes: &[u8]| {
+			!bytes.is_empty() &&
+				bytes.len() <= MAX_SOURCE_FIELD_LEN &&
+				str::from_utf8(bytes).is_ok()
+		};
+		match self {
+			PriceSource::CoinCap { asset_id } => text_ok(asset_id),
+			PriceSource::CryptoCompare { symbol } => text_ok(symbol),
+			PriceSource::JsonPath { url, pointer } =>
+				text_ok(url) && text_ok(pointer) && pointer.starts_with(b"/"),
+		}
+	}
+
+	/// Read the price out of a response body, `None` if it is malformed.
+	pub fn extract(&self, body: &[u8]) -> Option<Price> {
+		match self {
+			PriceSource::CoinCap { .. } => extract_pointer(body, b"/data/priceUsd"),
+			PriceSource::CryptoCompare { .. } => extract_pointer(body, b"/USD"),
+			PriceSource::JsonPath { pointer, .. } => extract_pointer(body, pointer),
+		}
+	}
+}
+
+/// Read the JSON string or number found at `pointer` as a price.
+fn extract_pointer(body: &[u8], pointer: &[u8]) -> Option<Price> {
+	let text = json::decimal_text(&json::pointer(body, pointer)?)?;
+	parse_price(str::from_utf8(&text).ok()?)
+}
@@ -0,0 +1,64 @@
+//! The jobs run by the offchain worker and the local storage keys used to schedule them.
+
+use crate::PriceSubmission;
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+
+/// A job the offchain worker can run.
+///
+/// The runtime lists the tasks to run together with their interval in
+/// [`Config::OffchainTasks`](crate::Config::OffchainTasks).
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum OffchainTask {
+	/// Submit the block number in a signed transaction.
+	SignedTx,
+	/// Submit the block number in an unsigned transaction.
+	UnsignedTx,
+	/// Submit the block number in an unsigned transaction with a signed payload.
+	UnsignedTxSignedPayload,
+	/// Fetch and cache the github organisation info.
+	GithubInfo,
+	/// Fetch the price of every registered asset and submit them.
+	PriceFeed,
+	/// Run every registered oracle job and submit the results.
+	OracleJobs,
+	/// Read the offchain indexed prices back and cache the time-weighted average price of
+	/// every registered asset, see [`indexing::twap_key`](crate::indexing::twap_key).
+	IndexedTwap,
+}
+
+impl OffchainTask {
+	fn name(&self) -> &'static [u8] {
+		match self {
+			OffchainTask::SignedTx => b"signed-tx",
+			OffchainTask::UnsignedTx => b"unsigned-tx",
+			OffchainTask::UnsignedTxSignedPayload => b"unsigned-tx-signed-payload",
+			OffchainTask::GithubInfo => b"gh-info",
+			OffchainTask::PriceFeed => b"price-feed",
+			OffchainTask::OracleJobs => b"oracle-jobs",
+			OffchainTask::IndexedTwap => b"indexed-twap",
+		}
+	}
+
+	/// Whether the task sends a signed transaction.
+	///
+	/// Signed transactions sent in the same block use the same account nonce, so only one of
+	/// them can make it into the pool.
+	pub fn is_signed(&self, price_submission: PriceSubmission) -> bool {
+		match self {
+			OffchainTask::SignedTx | OffchainTask::OracleJobs => true,
+			OffchainTask::PriceFeed => price_submission == PriceSubmission::Signed,
+			_ => false,
+		}
+	}
+
+	/// The local storage key holding the block number the task last ran at.
+	pub fn last_run_key(&self) -> Vec<u8> {
+		[&b"offchain-demo::task::"[..], self.name(), &b"::last-run"[..]].concat()
+	}
+
+	/// The local storage key of the lock held while the task runs.
+	pub fn lock_key(&self) -> Vec<u8> {
+		[&b"offchain-demo::task::"[..], self.name(), &b"::lock"[..]].concat()
+	}
+}
@@ -0,0 +1,968 @@
+use crate::{
+	crypto::TestAuthId, mock::*, Endpoint, Error, HttpMethod, IndexingData, JobResult, OffchainTask,
+	OracleJob, Payload, Price, PricePayload, PriceProvider, PriceSource, PriceSubmission, ResultKind,
+};
+use frame_support::{
+	assert_noop, assert_ok,
+	storage::migration::{get_storage_value, put_storage_value, take_storage_value},
+	traits::{Get, GetStorageVersion, Hooks, PalletInfoAccess, StorageVersion},
+	unsigned::ValidateUnsigned,
+	weights::Pays,
+	Blake2_128Concat, StorageHasher,
+};
+use frame_system::offchain::SignedPayload;
+use parity_scale_codec::{Decode, Encode};
+use sp_arithmetic::{FixedI128, Permill};
+use sp_core::{
+	offchain::{testing::PendingRequest, HttpRequestStatus, StorageKind},
+	sr25519,
+};
+use sp_runtime::transaction_validity::{InvalidTransaction, TransactionSource};
+use std::collections::VecDeque;
+
+const COINCAP_BODY: &[u8] = br#"{"data":{"id":"polkadot","priceUsd":"6.1"},"timestamp":1630000000000}"#;
+const CRYPTOCOMPARE_BODY: &[u8] = br#"{"USD":6.3}"#;
+const GITHUB_BODY: &[u8] =
+	br#"{"login":"substrate-developer-hub","blog":"https://substrate.dev","public_repos":40}"#;
+
+fn price(price: &str) -> Price {
+	crate::price::parse_price(price).unwrap()
+}
+
+fn dot_price_call(price: Price) -> Call {
+	Call::OcwDemo(crate::Call::submit_price_signed(vec![(b"DOT".to_vec(), price)]))
+}
+
+fn cached_github_info() -> Option<(Vec<u8>, Vec<u8>, u32)> {
+	sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, b"offchain-demo::gh-info")
+		.map(|info| Decode::decode(&mut &info[..]).unwrap())
+}
+
+#[test]
+fn signed_tx_submits_the_block_number() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::SignedTx, 3);
+
+		let txs = offchain.take_transactions();
+		assert_eq!(txs.len(), 1);
+		assert_eq!(txs[0].signature, Some((0, ())));
+		assert_eq!(txs[0].call, Call::OcwDemo(crate::Call::submit_number_signed(3)));
+	});
+}
+
+#[test]
+fn unsigned_tx_submits_the_block_number() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::UnsignedTx, 3);
+
+		let txs = offchain.take_transactions();
+		assert_eq!(txs.len(), 1);
+		assert_eq!(txs[0].signature, None);
+		assert_eq!(txs[0].call, Call::OcwDemo(crate::Call::submit_number_unsigned(3)));
+	});
+}
+
+#[test]
+fn unsigned_tx_with_signed_payload_submits_a_verifiable_payload() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::UnsignedTxSignedPayload, 3);
+
+		let txs = offchain.take_transactions();
+		assert_eq!(txs.len(), 1);
+		assert_eq!(txs[0].signature, None);
+		match &txs[0].call {
+			Call::OcwDemo(crate::Call::submit_number_unsigned_with_signed_payload(payload, signature)) => {
+				assert_eq!(payload, &Payload { number: 3, public: offchain.public });
+				assert!(SignedPayload::<Test>::verify::<TestAuthId>(payload, signature.clone()));
+			}
+			call => panic!("unexpected call {:?}", call),
+		}
+	});
+}
+
+#[test]
+fn github_info_is_fetched_once_and_cached() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	offchain.expect_get(GITHUB_URL, GITHUB_BODY);
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::GithubInfo, 3);
+		assert_eq!(
+			cached_github_info(),
+			Some((b"substrate-developer-hub".to_vec(), b"https://substrate.dev".to_vec(), 40))
+		);
+
+		// No request is expected this time, the cached copy is used.
+		run_offchain_task(OffchainTask::GithubInfo, 4);
+		assert!(offchain.take_transactions().is_empty());
+	});
+}
+
+#[test]
+fn github_info_is_not_cached_on_errors() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	offchain.expect_get(GITHUB_URL, br#"{"login":"substrate-developer-hub"}"#);
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::GithubInfo, 3);
+		assert_eq!(cached_github_info(), None);
+	});
+
+	offchain.fail(GITHUB_URL, HttpRequestStatus::Finished(404));
+	offchain.expect_get(GITHUB_URL, b"");
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::GithubInfo, 4);
+		assert_eq!(cached_github_info(), None);
+	});
+
+	offchain.fail(GITHUB_URL, HttpRequestStatus::DeadlineReached);
+	offchain.expect_get(GITHUB_URL, b"");
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::GithubInfo, 5);
+		assert_eq!(cached_github_info(), None);
+	});
+}
+
+#[test]
+fn endpoints_can_be_overridden_in_local_storage() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	offchain.state.write().expect_request(PendingRequest {
+		method: "GET".into(),
+		uri: "http://localhost:8080/gh".into(),
+		headers: vec![("User-Agent".into(), "ci".into())],
+		response: Some(GITHUB_BODY.to_vec()),
+		sent: true,
+		..Default::default()
+	});
+	ext.execute_with(|| {
+		let set = |endpoint: Endpoint, value: &[u8]| {
+			sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, &endpoint.storage_key(), value)
+		};
+		set(Endpoint::GithubUrl, b"http://localhost:8080/gh");
+		set(Endpoint::UserAgent, b"ci");
+
+		run_offchain_task(OffchainTask::GithubInfo, 3);
+		assert!(cached_github_info().is_some());
+	});
+}
+
+#[test]
+fn price_feed_submits_the_median_of_all_sources() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	offchain.expect_get(COINCAP_URL, COINCAP_BODY);
+	offchain.expect_get(CRYPTOCOMPARE_URL, CRYPTOCOMPARE_BODY);
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::PriceFeed, 3);
+
+		let txs = offchain.take_transactions();
+		assert_eq!(txs.len(), 1);
+		assert_eq!(txs[0].signature, Some((0, ())));
+		assert_eq!(txs[0].call, dot_price_call(price("6.2")));
+	});
+}
+
+#[test]
+fn price_feed_skips_sources_with_non_200_responses() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	offchain.expect_get(COINCAP_URL, b"");
+	offchain.expect_get(CRYPTOCOMPARE_URL, CRYPTOCOMPARE_BODY);
+	offchain.fail(COINCAP_URL, HttpRequestStatus::Finished(500));
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::PriceFeed, 3);
+
+		let txs = offchain.take_transactions();
+		assert_eq!(txs.len(), 1);
+		assert_eq!(txs[0].call, dot_price_call(price("6.3")));
+	});
+}
+
+#[test]
+fn price_feed_skips_sources_with_malformed_json() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	offchain.expect_get(COINCAP_URL, br#"{"data":{"id":"polkadot"}}"#);
+	offchain.expect_get(CRYPTOCOMPARE_URL, CRYPTOCOMPARE_BODY);
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::PriceFeed, 3);
+
+		let txs = offchain.take_transactions();
+		assert_eq!(txs.len(), 1);
+		assert_eq!(txs[0].call, dot_price_call(price("6.3")));
+	});
+}
+
+#[test]
+fn price_feed_submits_nothing_without_a_usable_source() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	offchain.expect_get(COINCAP_URL, b"");
+	offchain.expect_get(CRYPTOCOMPARE_URL, br#"{"USD":"#);
+	offchain.fail(COINCAP_URL, HttpRequestStatus::DeadlineReached);
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::PriceFeed, 3);
+		assert!(offchain.take_transactions().is_empty());
+	});
+}
+
+#[test]
+fn price_feed_skips_sources_that_time_out() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	offchain.expect_get(COINCAP_URL, COINCAP_BODY);
+	offchain.expect_get(CRYPTOCOMPARE_URL, b"");
+	offchain.fail(CRYPTOCOMPARE_URL, HttpRequestStatus::DeadlineReached);
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::PriceFeed, 3);
+
+		let txs = offchain.take_transactions();
+		assert_eq!(txs.len(), 1);
+		assert_eq!(txs[0].call, dot_price_call(price("6.1")));
+	});
+}
+
+#[test]
+fn tasks_run_once_per_interval() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		set_offchain_tasks(vec![(OffchainTask::UnsignedTx, 3)]);
+		for block in 1..=7 {
+			System::set_block_number(block);
+			<OcwDemo as frame_support::traits::Hooks<u64>>::offchain_worker(block);
+		}
+
+		let calls: Vec<Call> = offchain.take_transactions().into_iter().map(|tx| tx.call).collect();
+		assert_eq!(
+			calls,
+			vec![
+				Call::OcwDemo(crate::Call::submit_number_unsigned(1)),
+				Call::OcwDemo(crate::Call::submit_number_unsigned(4)),
+				Call::OcwDemo(crate::Call::submit_number_unsigned(7)),
+			]
+		);
+	});
+}
+
+#[test]
+fn validate_unsigned_accepts_the_worker_calls() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		let call = crate::Call::submit_number_unsigned(3);
+		assert_ok!(OcwDemo::validate_unsigned(TransactionSource::External, &call));
+
+		let payload = Payload { number: 3, public: offchain.public };
+		let signature = SignedPayload::<Test>::sign::<TestAuthId>(&payload).unwrap();
+		let call = crate::Call::submit_number_unsigned_with_signed_payload(payload, signature);
+		assert_ok!(OcwDemo::validate_unsigned(TransactionSource::External, &call));
+	});
+}
+
+#[test]
+fn validate_unsigned_rejects_forged_payloads() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		let payload = Payload { number: 3, public: offchain.public };
+		let signature = SignedPayload::<Test>::sign::<TestAuthId>(&payload).unwrap();
+		let forged = Payload { number: 4, public: offchain.public };
+		let call = crate::Call::submit_number_unsigned_with_signed_payload(forged, signature);
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::BadProof.into())
+		);
+	});
+}
+
+#[test]
+fn validate_unsigned_rejects_other_calls() {
+	new_test_ext().execute_with(|| {
+		let call = crate::Call::submit_number_signed(3);
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::Call.into())
+		);
+	});
+}
+
+fn dot_price_payload(public: sr25519::Public, block_number: u64) -> PricePayload<sr25519::Public, u64> {
+	PricePayload { prices: vec![(b"DOT".to_vec(), price("6.2"))], block_number, public }
+}
+
+#[test]
+fn price_feed_can_submit_unsigned_with_signed_payload() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	offchain.expect_get(COINCAP_URL, COINCAP_BODY);
+	offchain.expect_get(CRYPTOCOMPARE_URL, CRYPTOCOMPARE_BODY);
+	ext.execute_with(|| {
+		set_price_submission(PriceSubmission::UnsignedSignedPayload);
+		run_offchain_task(OffchainTask::PriceFeed, 3);
+
+		let txs = offchain.take_transactions();
+		assert_eq!(txs.len(), 1);
+		assert_eq!(txs[0].signature, None);
+		match &txs[0].call {
+			Call::OcwDemo(crate::Call::submit_price_unsigned_with_signed_payload(payload, signature)) => {
+				assert_eq!(payload, &dot_price_payload(offchain.public, 3));
+				assert!(SignedPayload::<Test>::verify::<TestAuthId>(payload, signature.clone()));
+			}
+			call => panic!("unexpected call {:?}", call),
+		}
+	});
+}
+
+#[test]
+fn unsigned_prices_are_accepted_from_oracles_only() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		System::set_block_number(3);
+		let payload = dot_price_payload(offchain.public, 3);
+		let signature = SignedPayload::<Test>::sign::<TestAuthId>(&payload).unwrap();
+		let call = crate::Call::submit_price_unsigned_with_signed_payload(payload, signature);
+		assert_ok!(OcwDemo::validate_unsigned(TransactionSource::External, &call));
+
+		crate::Oracles::<Test>::kill();
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+	});
+}
+
+#[test]
+fn unsigned_prices_reject_forged_and_future_payloads() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		System::set_block_number(3);
+		let payload = dot_price_payload(offchain.public, 3);
+		let signature = SignedPayload::<Test>::sign::<TestAuthId>(&payload).unwrap();
+		let mut forged = payload.clone();
+		forged.prices[0].1 = price("60");
+		let call = crate::Call::submit_price_unsigned_with_signed_payload(forged, signature);
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::BadProof.into())
+		);
+
+		let payload = dot_price_payload(offchain.public, 4);
+		let signature = SignedPayload::<Test>::sign::<TestAuthId>(&payload).unwrap();
+		let call = crate::Call::submit_price_unsigned_with_signed_payload(payload, signature);
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::Future.into())
+		);
+	});
+}
+
+#[test]
+fn unsigned_prices_are_rate_limited_per_oracle() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		System::set_block_number(3);
+		let payload = dot_price_payload(offchain.public, 3);
+		let signature = SignedPayload::<Test>::sign::<TestAuthId>(&payload).unwrap();
+		assert_noop!(
+			OcwDemo::submit_price_unsigned_with_signed_payload(
+				Origin::signed(offchain.public),
+				payload.clone(),
+				signature.clone()
+			),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(OcwDemo::submit_price_unsigned_with_signed_payload(Origin::none(), payload, signature));
+		assert_eq!(OcwDemo::next_unsigned_at(offchain.public), 3 + UnsignedInterval::get());
+		assert_eq!(OcwDemo::prices(b"DOT".to_vec()).last().unwrap().price, price("6.2"));
+
+		System::set_block_number(7);
+		let payload = dot_price_payload(offchain.public, 7);
+		let signature = SignedPayload::<Test>::sign::<TestAuthId>(&payload).unwrap();
+		let call = crate::Call::submit_price_unsigned_with_signed_payload(payload, signature);
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::Stale.into())
+		);
+
+		System::set_block_number(8);
+		let payload = dot_price_payload(offchain.public, 8);
+		let signature = SignedPayload::<Test>::sign::<TestAuthId>(&payload).unwrap();
+		let call = crate::Call::submit_price_unsigned_with_signed_payload(payload, signature);
+		assert_ok!(OcwDemo::validate_unsigned(TransactionSource::External, &call));
+	});
+}
+
+fn fx_job() -> OracleJob {
+	OracleJob {
+		url: b"https://fx.example.com/latest".to_vec(),
+		method: HttpMethod::Post,
+		headers: vec![(b"Content-Type".to_vec(), b"application/json".to_vec())],
+		body: br#"{"base":"USD"}"#.to_vec(),
+		pointer: b"/rates/EUR".to_vec(),
+		kind: ResultKind::Number,
+	}
+}
+
+#[test]
+fn oracle_jobs_are_run_and_submitted_per_job() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	offchain.state.write().expect_request(PendingRequest {
+		method: "POST".into(),
+		uri: "https://fx.example.com/latest".into(),
+		headers: vec![("Content-Type".into(), "application/json".into())],
+		body: br#"{"base":"USD"}"#.to_vec(),
+		response: Some(br#"{"rates":{"EUR":0.85}}"#.to_vec()),
+		sent: true,
+		..Default::default()
+	});
+	offchain.expect_get("https://api.example.com/weather", br#"{"city":"Oslo"}"#);
+	ext.execute_with(|| {
+		assert_ok!(OcwDemo::register_job(Origin::root(), fx_job()));
+		let weather = OracleJob {
+			url: b"https://api.example.com/weather".to_vec(),
+			method: HttpMethod::Get,
+			headers: vec![(b"User-Agent".to_vec(), b"jimmychu0807".to_vec())],
+			body: Vec::new(),
+			pointer: b"/city".to_vec(),
+			kind: ResultKind::Bytes,
+		};
+		assert_ok!(OcwDemo::register_job(Origin::root(), weather));
+
+		run_offchain_task(OffchainTask::OracleJobs, 3);
+
+		let txs = offchain.take_transactions();
+		assert_eq!(txs.len(), 1);
+		assert_eq!(
+			txs[0].call,
+			Call::OcwDemo(crate::Call::submit_job_results(vec![
+				(0, JobResult::Number(FixedI128::from_inner(850_000_000_000_000_000))),
+				(1, JobResult::Bytes(b"Oslo".to_vec())),
+			]))
+		);
+	});
+}
+
+#[test]
+fn job_results_are_accepted_from_oracles_for_known_jobs() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		let result = JobResult::Bytes(b"Oslo".to_vec());
+		assert_noop!(
+			OcwDemo::submit_job_results(Origin::signed(offchain.public), vec![(0, result.clone())]),
+			Error::<Test>::UnknownJob
+		);
+		assert_noop!(
+			OcwDemo::register_job(Origin::root(), OracleJob { url: Vec::new(), ..fx_job() }),
+			Error::<Test>::InvalidJob
+		);
+
+		assert_ok!(OcwDemo::register_job(Origin::root(), fx_job()));
+		assert_noop!(
+			OcwDemo::submit_job_results(
+				Origin::signed(sr25519::Public::from_raw([7; 32])),
+				vec![(0, result.clone())]
+			),
+			Error::<Test>::NotOracle
+		);
+		assert_ok!(OcwDemo::submit_job_results(Origin::signed(offchain.public), vec![(0, result.clone())]));
+		assert_eq!(OcwDemo::job_results(0), Some((result, 1)));
+
+		assert_ok!(OcwDemo::remove_job(Origin::root(), 0));
+		assert_eq!(OcwDemo::job_results(0), None);
+	});
+}
+
+#[test]
+fn job_results_are_bounded_unique_and_once_per_block() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		for _ in 0..3 {
+			assert_ok!(OcwDemo::register_job(Origin::root(), fx_job()));
+		}
+		let result = JobResult::Bytes(b"Oslo".to_vec());
+		let submit = |ids: &[u32]| {
+			let results = ids.iter().map(|id| (*id, result.clone())).collect();
+			OcwDemo::submit_job_results(Origin::signed(offchain.public), results)
+		};
+
+		// `MaxJobResults` is 2.
+		assert_noop!(submit(&[0, 1, 2]), Error::<Test>::TooManyJobResults);
+		assert_noop!(submit(&[1, 1]), Error::<Test>::DuplicateJobResult);
+
+		assert_ok!(submit(&[0, 1]));
+		assert_noop!(submit(&[2]), Error::<Test>::JobResultsAlreadySubmitted);
+
+		System::set_block_number(2);
+		assert_ok!(submit(&[2]));
+		assert_eq!(OcwDemo::job_results(2), Some((result.clone(), 2)));
+	});
+}
+
+#[test]
+fn price_history_keeps_the_newest_prices() {
+	new_test_ext().execute_with(|| {
+		for (now, dot) in [(1_000, "10"), (2_000, "11"), (3_000, "12"), (4_000, "13")].iter() {
+			set_now(*now);
+			assert_ok!(OcwDemo::force_price(Origin::root(), b"DOT".to_vec(), price(dot)));
+		}
+
+		let history: Vec<Price> = OcwDemo::prices(b"DOT".to_vec()).iter().map(|point| point.price).collect();
+		assert_eq!(history.len() as u32, HistoryLength::get());
+		assert_eq!(history, vec![price("11"), price("12"), price("13")]);
+		assert_eq!(<OcwDemo as PriceProvider<u64>>::latest(&b"DOT".to_vec()), Some(price("13")));
+	});
+}
+
+#[test]
+fn price_provider_aggregates_over_a_window() {
+	new_test_ext().execute_with(|| {
+		for (now, dot) in [(1_000, "12"), (2_000, "10"), (3_000, "11")].iter() {
+			set_now(*now);
+			assert_ok!(OcwDemo::force_price(Origin::root(), b"DOT".to_vec(), price(dot)));
+		}
+		let dot = b"DOT".to_vec();
+
+		assert_eq!(<OcwDemo as PriceProvider<u64>>::min(&dot, 5_000), Some(price("10")));
+		assert_eq!(<OcwDemo as PriceProvider<u64>>::max(&dot, 5_000), Some(price("12")));
+		assert_eq!(<OcwDemo as PriceProvider<u64>>::mean(&dot, 5_000), Some(price("11")));
+
+		// Only the last two prices fall inside the window.
+		assert_eq!(<OcwDemo as PriceProvider<u64>>::max(&dot, 1_000), Some(price("11")));
+		assert_eq!(<OcwDemo as PriceProvider<u64>>::mean(&dot, 1_000), Some(price("10.5")));
+
+		assert_eq!(<OcwDemo as PriceProvider<u64>>::min(&b"KSM".to_vec(), 5_000), None);
+	});
+}
+
+#[test]
+fn accepted_prices_are_offchain_indexed() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		System::set_block_number(2);
+		set_now(1_000);
+		assert_ok!(OcwDemo::submit_price_signed(
+			Origin::signed(offchain.public),
+			vec![(b"DOT".to_vec(), price("10"))]
+		));
+		System::set_block_number(4);
+		set_now(3_000);
+		assert_ok!(OcwDemo::force_price(Origin::root(), b"DOT".to_vec(), price("11")));
+	});
+	ext.persist_offchain_overlay();
+
+	ext.execute_with(|| {
+		let history = crate::indexing::history::<u64, sr25519::Public>(b"DOT", 1, 5);
+		assert_eq!(
+			history,
+			vec![
+				(
+					2,
+					IndexingData {
+						price: price("10"),
+						timestamp: 1_000,
+						answers: vec![(offchain.public, price("10"))]
+					}
+				),
+				(4, IndexingData { price: price("11"), timestamp: 3_000, answers: vec![] }),
+			]
+		);
+		assert!(crate::indexing::history::<u64, sr25519::Public>(b"KSM", 1, 5).is_empty());
+
+		// 10 for 2s and 11 for 2s.
+		offchain.state.write().timestamp = sp_core::offchain::Timestamp::from_unix_millis(5_000);
+		assert_eq!(OcwDemo::indexed_twap(&b"DOT".to_vec(), 5), Some(price("10.5")));
+		// Block 2 is more than `IndexedHistory` blocks before block 23.
+		assert_eq!(OcwDemo::indexed_twap(&b"DOT".to_vec(), 23), Some(price("11")));
+
+		run_offchain_task(OffchainTask::IndexedTwap, 5);
+		let cached = sp_io::offchain::local_storage_get(
+			StorageKind::PERSISTENT,
+			&crate::indexing::twap_key(b"DOT"),
+		);
+		assert_eq!(cached.map(|twap| Price::decode(&mut &twap[..]).unwrap()), Some(price("10.5")));
+	});
+}
+
+#[test]
+fn oracle_price_submissions_are_free() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		let info = OcwDemo::submit_price_signed(
+			Origin::signed(offchain.public),
+			vec![(b"DOT".to_vec(), price("6.2"))],
+		)
+		.unwrap();
+		assert_eq!(info.pays_fee, Pays::No);
+		assert_eq!(OcwDemo::oracle_counters(offchain.public).submitted, 1);
+
+		let outsider = sr25519::Public::from_raw([7; 32]);
+		let err =
+			OcwDemo::submit_price_signed(Origin::signed(outsider), vec![(b"DOT".to_vec(), price("6.2"))])
+				.unwrap_err();
+		assert_eq!(err.error, Error::<Test>::NotOracle.into());
+		assert_eq!(err.post_info.pays_fee, Pays::Yes);
+	});
+}
+
+#[test]
+fn oracles_missing_rounds_are_reported() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		let absent = sr25519::Public::from_raw([7; 32]);
+		assert_ok!(OcwDemo::add_oracle(Origin::root(), absent));
+
+		// The quorum is one, so each submission finalizes a round `absent` did not answer.
+		for _ in 0..3 {
+			assert_ok!(OcwDemo::submit_price_signed(
+				Origin::signed(offchain.public),
+				vec![(b"DOT".to_vec(), price("6.2"))]
+			));
+		}
+		assert_eq!(missed_rounds(), vec![(absent, MaxMissedRounds::get())]);
+		System::assert_has_event(Event::OcwDemo(crate::Event::OracleMissedRounds(absent, 2)));
+		assert_eq!(OcwDemo::oracle_counters(absent).missed_in_a_row, 1);
+		assert_eq!(OcwDemo::oracle_counters(offchain.public).missed_in_a_row, 0);
+		assert_eq!(OcwDemo::oracle_counters(offchain.public).submitted, 3);
+
+		assert_ok!(OcwDemo::remove_oracle(Origin::root(), absent));
+		assert_eq!(OcwDemo::oracle_counters(absent), Default::default());
+	});
+}
+
+#[test]
+fn github_info_fetch_retries_transient_failures() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	offchain.expect_get(GITHUB_URL, b"");
+	offchain.expect_get(GITHUB_URL, GITHUB_BODY);
+	offchain.fail_once(GITHUB_URL, HttpRequestStatus::Finished(503));
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::GithubInfo, 3);
+		assert!(cached_github_info().is_some());
+		// The retry waited for the backoff.
+		assert_eq!(sp_io::offchain::timestamp().unix_millis(), HttpBackoff::get());
+	});
+}
+
+#[test]
+fn github_info_fetch_gives_up_after_the_last_retry() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	for _ in 0..=HttpRetries::get() {
+		offchain.expect_get(GITHUB_URL, b"");
+	}
+	offchain.fail(GITHUB_URL, HttpRequestStatus::Finished(429));
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::GithubInfo, 3);
+		assert_eq!(cached_github_info(), None);
+		// Backoffs of 100ms and 200ms.
+		assert_eq!(sp_io::offchain::timestamp().unix_millis(), 300);
+	});
+}
+
+#[test]
+fn price_feed_skips_sources_with_oversized_responses() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	let padding = vec![b' '; MaxResponseSize::get() as usize];
+	offchain.expect_get(COINCAP_URL, &[COINCAP_BODY, &padding[..]].concat());
+	offchain.expect_get(CRYPTOCOMPARE_URL, CRYPTOCOMPARE_BODY);
+	ext.execute_with(|| {
+		run_offchain_task(OffchainTask::PriceFeed, 3);
+
+		let txs = offchain.take_transactions();
+		assert_eq!(txs.len(), 1);
+		assert_eq!(txs[0].call, dot_price_call(price("6.3")));
+	});
+}
+
+#[test]
+fn unchanged_responses_are_served_from_the_etag_cache() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	offchain.state.write().expect_request(PendingRequest {
+		method: "GET".into(),
+		uri: GITHUB_URL.into(),
+		headers: vec![("User-Agent".into(), "jimmychu0807".into())],
+		response: Some(GITHUB_BODY.to_vec()),
+		response_headers: vec![("ETag".into(), "\"v1\"".into())],
+		sent: true,
+		..Default::default()
+	});
+	offchain.state.write().expect_request(PendingRequest {
+		method: "GET".into(),
+		uri: GITHUB_URL.into(),
+		headers: vec![
+			("User-Agent".into(), "jimmychu0807".into()),
+			("If-None-Match".into(), "\"v1\"".into()),
+		],
+		response: Some(Vec::new()),
+		sent: true,
+		..Default::default()
+	});
+	ext.execute_with(|| {
+		let request = ocw_http::Request::get(GITHUB_URL)
+			.header("User-Agent", "jimmychu0807")
+			.max_body(MaxResponseSize::get())
+			.cache_etag();
+		let get = || request.send::<crate::OffchainHost>().map(|response| response.body);
+
+		assert_eq!(get(), Ok(GITHUB_BODY.to_vec()));
+		offchain.fail_once(GITHUB_URL, HttpRequestStatus::Finished(304));
+		assert_eq!(get(), Ok(GITHUB_BODY.to_vec()));
+	});
+}
+
+/// Store `prices` as the single price history of the pallet before it kept a feed per asset.
+fn put_single_asset_prices(prices: Vec<(u64, Permill)>) {
+	let pallet = <OcwDemo as PalletInfoAccess>::name().as_bytes();
+	put_storage_value(pallet, b"Prices", &[], VecDeque::from(prices));
+	StorageVersion::new(0).put::<OcwDemo>();
+}
+
+#[test]
+fn single_asset_prices_move_to_the_default_asset() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(5);
+		set_now(1_000);
+		put_single_asset_prices(vec![(6, Permill::from_percent(10)), (7, Permill::from_percent(0))]);
+
+		crate::migrations::v0::migrate::<Test>();
+
+		let pallet = <OcwDemo as PalletInfoAccess>::name().as_bytes();
+		assert_eq!(take_storage_value::<VecDeque<(u64, Permill)>>(pallet, b"Prices", &[]), None);
+		// Version 1 prices: `(price, block_number, timestamp)`.
+		let key = Blake2_128Concat::hash(&b"DOT".to_vec().encode());
+		assert_eq!(
+			get_storage_value::<Vec<((u64, Permill), u64, u64)>>(pallet, b"Prices", &key),
+			Some(vec![((6, Permill::from_percent(10)), 5, 1_000), ((7, Permill::from_percent(0)), 5, 1_000)]),
+		);
+	});
+}
+
+#[test]
+fn runtime_upgrade_converts_single_asset_prices() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(5);
+		set_now(1_000);
+		put_single_asset_prices(vec![(6, Permill::from_percent(10)), (7, Permill::from_parts(250_000))]);
+
+		<OcwDemo as Hooks<u64>>::on_runtime_upgrade();
+
+		let pallet = <OcwDemo as PalletInfoAccess>::name().as_bytes();
+		assert_eq!(take_storage_value::<VecDeque<(u64, Permill)>>(pallet, b"Prices", &[]), None);
+		let history: Vec<(Price, u64, u64)> = OcwDemo::prices(b"DOT".to_vec())
+			.iter()
+			.map(|point| (point.price, point.block_number, point.timestamp))
+			.collect();
+		assert_eq!(history, vec![(price("6.1"), 5, 1_000), (price("7.25"), 5, 1_000)]);
+		assert_eq!(OcwDemo::on_chain_storage_version(), 2);
+	});
+}
+
+fn submit_dot_price(oracle: sr25519::Public, dot: &str) {
+	assert_ok!(OcwDemo::submit_price_signed(Origin::signed(oracle), vec![(b"DOT".to_vec(), price(dot))]));
+}
+
+fn dot_history() -> Vec<Price> {
+	OcwDemo::prices(b"DOT".to_vec()).iter().map(|point| point.price).collect()
+}
+
+#[test]
+fn rounds_moving_more_than_max_price_change_are_rejected() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		submit_dot_price(offchain.public, "10");
+
+		// `MaxPriceChange` is 20%.
+		System::set_block_number(2);
+		submit_dot_price(offchain.public, "12.5");
+
+		System::assert_has_event(Event::OcwDemo(crate::Event::PriceRejected(
+			b"DOT".to_vec(),
+			1,
+			price("12.5"),
+			price("10"),
+		)));
+		assert_eq!(dot_history(), vec![price("10")]);
+		assert_eq!(OcwDemo::last_accepted(b"DOT".to_vec()), Some(1));
+		assert_eq!(OcwDemo::current_round(b"DOT".to_vec()), 2);
+	});
+}
+
+#[test]
+fn forced_price_resumes_a_rejected_feed() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		submit_dot_price(offchain.public, "10");
+		System::set_block_number(2);
+		submit_dot_price(offchain.public, "12.5");
+		assert_eq!(dot_history(), vec![price("10")]);
+
+		assert_ok!(OcwDemo::force_price(Origin::root(), b"DOT".to_vec(), price("12.5")));
+		System::set_block_number(3);
+		submit_dot_price(offchain.public, "13");
+
+		System::assert_has_event(Event::OcwDemo(crate::Event::PriceFinalized(
+			b"DOT".to_vec(),
+			2,
+			price("13"),
+		)));
+		assert_eq!(dot_history(), vec![price("10"), price("12.5"), price("13")]);
+	});
+}
+
+#[test]
+fn feeds_without_an_accepted_price_for_stale_price_age_blocks_are_reported_once() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		let dot = b"DOT".to_vec();
+		submit_dot_price(offchain.public, "10");
+		let max_age = <StalePriceAge as Get<u64>>::get();
+		let stale_events = || {
+			System::events()
+				.into_iter()
+				.filter(|record| {
+					record.event == Event::OcwDemo(crate::Event::PriceFeedStale(dot.clone(), 1))
+				})
+				.count()
+		};
+
+		for block in 2..=max_age + 1 {
+			System::set_block_number(block);
+			OcwDemo::on_initialize(block);
+		}
+		assert_eq!(stale_events(), 0);
+		assert!(!<OcwDemo as PriceProvider<u64>>::is_stale(&dot, max_age));
+
+		for block in max_age + 2..=max_age + 5 {
+			System::set_block_number(block);
+			OcwDemo::on_initialize(block);
+		}
+		assert_eq!(stale_events(), 1);
+		assert!(<OcwDemo as PriceProvider<u64>>::is_stale(&dot, max_age));
+	});
+}
+
+#[test]
+fn rounds_finalize_at_quorum_with_the_median_answer() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		set_oracle_quorum(3);
+		let (second, third) = (sr25519::Public::from_raw([7; 32]), sr25519::Public::from_raw([8; 32]));
+		assert_ok!(OcwDemo::add_oracle(Origin::root(), second));
+		assert_ok!(OcwDemo::add_oracle(Origin::root(), third));
+
+		submit_dot_price(offchain.public, "10");
+		submit_dot_price(second, "15");
+		// Below quorum the round stays open.
+		assert_eq!(OcwDemo::current_round(b"DOT".to_vec()), 0);
+		assert_eq!(OcwDemo::round_answers(b"DOT".to_vec(), 0).len(), 2);
+		assert!(dot_history().is_empty());
+		assert_noop!(
+			OcwDemo::submit_price_signed(
+				Origin::signed(second),
+				vec![(b"DOT".to_vec(), price("16"))]
+			),
+			Error::<Test>::AlreadyAnswered
+		);
+
+		submit_dot_price(third, "11");
+		System::assert_has_event(Event::OcwDemo(crate::Event::PriceFinalized(
+			b"DOT".to_vec(),
+			0,
+			price("11"),
+		)));
+		assert_eq!(dot_history(), vec![price("11")]);
+		assert_eq!(OcwDemo::current_round(b"DOT".to_vec()), 1);
+		assert!(OcwDemo::round_answers(b"DOT".to_vec(), 0).is_empty());
+	});
+}
+
+#[test]
+fn oracle_set_changes_are_checked() {
+	new_test_ext().execute_with(|| {
+		let oracle = |seed: u8| sr25519::Public::from_raw([seed; 32]);
+		assert_ok!(OcwDemo::add_oracle(Origin::root(), oracle(1)));
+		assert_noop!(OcwDemo::add_oracle(Origin::root(), oracle(1)), Error::<Test>::AlreadyOracle);
+
+		// `MaxOracles` is 4.
+		for seed in 2..=4 {
+			assert_ok!(OcwDemo::add_oracle(Origin::root(), oracle(seed)));
+		}
+		assert_noop!(OcwDemo::add_oracle(Origin::root(), oracle(5)), Error::<Test>::TooManyOracles);
+
+		assert_noop!(OcwDemo::remove_oracle(Origin::root(), oracle(5)), Error::<Test>::NotOracle);
+		assert_ok!(OcwDemo::remove_oracle(Origin::root(), oracle(4)));
+		assert_ok!(OcwDemo::add_oracle(Origin::root(), oracle(5)));
+		assert_eq!(OcwDemo::oracles().len(), 4);
+	});
+}
+
+fn json_source(url: &[u8], pointer: &[u8]) -> PriceSource {
+	PriceSource::JsonPath { url: url.to_vec(), pointer: pointer.to_vec() }
+}
+
+#[test]
+fn assets_are_registered_with_bounded_valid_sources() {
+	new_test_ext().execute_with(|| {
+		let source = json_source(b"https://example.com/ksm", b"/data/price");
+		assert_noop!(
+			OcwDemo::register_asset(Origin::root(), vec![b'K'; 17], vec![source.clone()]),
+			Error::<Test>::SymbolTooLong
+		);
+		// `MaxSources` is 3.
+		assert_noop!(
+			OcwDemo::register_asset(Origin::root(), b"KSM".to_vec(), vec![source.clone(); 4]),
+			Error::<Test>::TooManySources
+		);
+		for invalid in vec![
+			json_source(b"https://example.com/ksm", b"data/price"),
+			json_source(b"https://example.com/ksm", b"/data/\xff"),
+			json_source(b"", b"/data/price"),
+			PriceSource::CoinCap { asset_id: vec![] },
+			PriceSource::CryptoCompare { symbol: vec![b'K'; 1025] },
+		] {
+			assert_noop!(
+				OcwDemo::register_asset(
+					Origin::root(),
+					b"KSM".to_vec(),
+					vec![source.clone(), invalid]
+				),
+				Error::<Test>::InvalidSource
+			);
+		}
+
+		assert_ok!(OcwDemo::register_asset(
+			Origin::root(),
+			b"KSM".to_vec(),
+			vec![source.clone(); 3]
+		));
+		System::assert_last_event(Event::OcwDemo(crate::Event::AssetRegistered(b"KSM".to_vec())));
+		assert_eq!(OcwDemo::assets(b"KSM".to_vec()), Some(vec![source; 3]));
+	});
+}
+
+#[test]
+fn unknown_assets_cannot_be_deregistered_or_forced() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			OcwDemo::deregister_asset(Origin::root(), b"KSM".to_vec()),
+			Error::<Test>::UnknownAsset
+		);
+		assert_noop!(
+			OcwDemo::force_price(Origin::root(), b"KSM".to_vec(), price("30")),
+			Error::<Test>::UnknownAsset
+		);
+	});
+}
+
+#[test]
+fn deregistering_an_asset_drops_its_open_round_and_keeps_its_prices() {
+	let (mut ext, offchain) = new_offchain_test_ext();
+	ext.execute_with(|| {
+		let dot = b"DOT".to_vec();
+		submit_dot_price(offchain.public, "10");
+		set_oracle_quorum(2);
+		System::set_block_number(2);
+		submit_dot_price(offchain.public, "10.5");
+		assert_eq!(OcwDemo::current_round(dot.clone()), 1);
+		assert_eq!(OcwDemo::round_answers(dot.clone(), 1).len(), 1);
+
+		assert_ok!(OcwDemo::deregister_asset(Origin::root(), dot.clone()));
+		System::assert_last_event(Event::OcwDemo(crate::Event::AssetDeregistered(dot.clone())));
+		assert_eq!(OcwDemo::assets(dot.clone()), None);
+		assert_eq!(OcwDemo::current_round(dot.clone()), 0);
+		assert!(OcwDemo::round_answers(dot.clone(), 1).is_empty());
+		assert_eq!(dot_history(), vec![price("10")]);
+	});
+}
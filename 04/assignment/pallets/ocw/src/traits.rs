@@ -0,0 +1,69 @@
+//! Traits other pallets use to consume the price feeds.
+
+use crate::{AssetSymbol, Price};
+
+/// Read access to the finalized price feeds.
+pub trait PriceProvider<BlockNumber> {
+	/// The latest finalized price of `asset`.
+	fn latest(asset: &AssetSymbol) -> Option<Price>;
+
+	/// The time-weighted average price of `asset` over the last `window` milliseconds.
+	///
+	/// Only the prices still kept in the history count, so a window reaching further back
+	/// than the oldest kept price averages over less time than asked for.
+	fn twap(asset: &AssetSymbol, window: u64) -> Option<Price>;
+
+	/// The lowest price of `asset` finalized in the last `window` milliseconds.
+	fn min(asset: &AssetSymbol, window: u64) -> Option<Price>;
+
+	/// The highest price of `asset` finalized in the last `window` milliseconds.
+	fn max(asset: &AssetSymbol, window: u64) -> Option<Price>;
+
+	/// The mean of the prices of `asset` finalized in the last `window` milliseconds.
+	///
+	/// Unlike [`PriceProvider::twap`] every price counts the same however long it lasted.
+	fn mean(asset: &AssetSymbol, window: u64) -> Option<Price>;
+
+	/// Whether `asset` has had no price accepted in the last `max_age` blocks.
+	///
+	/// An asset that never had a price is stale.
+	fn is_stale(asset: &AssetSymbol, max_age: BlockNumber) -> bool;
+}
+
+/// No price feeds, for runtimes and tests that do not need them.
+impl<BlockNumber> PriceProvider<BlockNumber> for () {
+	fn latest(_asset: &AssetSymbol) -> Option<Price> {
+		None
+	}
+
+	fn twap(_asset: &AssetSymbol, _window: u64) -> Option<Price> {
+		None
+	}
+
+	fn min(_asset: &AssetSymbol, _window: u64) -> Option<Price> {
+		None
+	}
+
+	fn max(_asset: &AssetSymbol, _window: u64) -> Option<Price> {
+		None
+	}
+
+	fn mean(_asset: &AssetSymbol, _window: u64) -> Option<Price> {
+		None
+	}
+
+	fn is_stale(_asset: &AssetSymbol, _max_age: BlockNumber) -> bool {
+		true
+	}
+}
+
+/// Reacts to oracles that keep missing price rounds, e.g. by slashing their bond.
+pub trait OnMissedRounds<AccountId> {
+	/// `oracle` did not answer any of the last `missed` rounds that were finalized.
+	fn on_missed_rounds(oracle: &AccountId, missed: u32);
+}
+
+/// Do nothing beyond the `OracleMissedRounds` event.
+impl<AccountId> OnMissedRounds<AccountId> for () {
+	fn on_missed_rounds(_oracle: &AccountId, _missed: u32) {}
+}
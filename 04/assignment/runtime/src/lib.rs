@@ -17,7 +17,7 @@ use sp_runtime::{
 	traits::{
 		AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, Verify
 	},
-	transaction_validity::{TransactionSource, TransactionValidity},
+	transaction_validity::{TransactionPriority, TransactionSource, TransactionValidity},
 	ApplyExtrinsicResult, MultiSignature, SaturatedConversion,
 };
 use sp_std::prelude::*;
@@ -276,11 +276,66 @@ impl pallet_template::Config for Runtime {
 	type Event = Event;
 }
 
+parameter_types! {
+	pub const MaxSourceDeviation: Permill = Permill::from_percent(5);
+	pub const MinPriceSources: u32 = 1;
+	pub const MaxOracles: u32 = 32;
+	pub const OracleQuorum: u32 = 1;
+	pub const MaxMissedRounds: u32 = 100;
+	pub const MaxPriceChange: Permill = Permill::from_percent(20);
+	pub const StalePriceAge: BlockNumber = 10 * MINUTES;
+	pub const HistoryLength: u32 = 10;
+	pub const IndexedHistory: BlockNumber = HOURS;
+	// The price feed runs every block.
+	pub OffchainTasks: Vec<(pallet_ocw::OffchainTask, BlockNumber)> = vec![
+		(pallet_ocw::OffchainTask::PriceFeed, 1),
+		(pallet_ocw::OffchainTask::SignedTx, 5),
+		(pallet_ocw::OffchainTask::UnsignedTx, 5),
+		(pallet_ocw::OffchainTask::UnsignedTxSignedPayload, 5),
+		(pallet_ocw::OffchainTask::GithubInfo, 5),
+		(pallet_ocw::OffchainTask::OracleJobs, 10),
+		(pallet_ocw::OffchainTask::IndexedTwap, 10),
+	];
+	pub const OffchainTimeBudget: u64 = 5_000;
+	pub const HttpRetries: u32 = 3;
+	pub const HttpBackoff: u64 = 200;
+	pub const MaxResponseSize: u32 = 64 * 1024;
+	pub const MaxJobResults: u32 = 16;
+	pub const MaxSources: u32 = 8;
+	// Prices are sent unsigned, so the price feed does not clash with `SignedTx` on the account
+	// nonce.
+	pub const PriceSubmission: pallet_ocw::PriceSubmission = pallet_ocw::PriceSubmission::UnsignedSignedPayload;
+	pub const OcwUnsignedInterval: BlockNumber = 1;
+	pub const OcwUnsignedPriority: TransactionPriority = TransactionPriority::max_value() / 2;
+}
+
 /// For pallet-ocw
 impl pallet_ocw::Config for Runtime {
 	type AuthorityId = pallet_ocw::crypto::TestAuthId;
 	type Call = Call;
 	type Event = Event;
+	type UnixTime = Timestamp;
+	type MaxSourceDeviation = MaxSourceDeviation;
+	type MinPriceSources = MinPriceSources;
+	type OracleOrigin = frame_system::EnsureRoot<AccountId>;
+	type MaxOracles = MaxOracles;
+	type OracleQuorum = OracleQuorum;
+	type MaxMissedRounds = MaxMissedRounds;
+	type OnMissedRounds = ();
+	type MaxPriceChange = MaxPriceChange;
+	type StalePriceAge = StalePriceAge;
+	type HistoryLength = HistoryLength;
+	type IndexedHistory = IndexedHistory;
+	type OffchainTasks = OffchainTasks;
+	type OffchainTimeBudget = OffchainTimeBudget;
+	type HttpRetries = HttpRetries;
+	type HttpBackoff = HttpBackoff;
+	type MaxResponseSize = MaxResponseSize;
+	type MaxJobResults = MaxJobResults;
+	type MaxSources = MaxSources;
+	type PriceSubmission = PriceSubmission;
+	type UnsignedInterval = OcwUnsignedInterval;
+	type UnsignedPriority = OcwUnsignedPriority;
 }
 
 impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Runtime
@@ -350,7 +405,7 @@ construct_runtime!(
 		Sudo: pallet_sudo::{Pallet, Call, Config<T>, Storage, Event<T>},
 		// Include the custom logic from the pallet-template in the runtime.
 		TemplateModule: pallet_template::{Pallet, Call, Storage, Event<T>},
-		OcwDemo: pallet_ocw::{Pallet, Call, Storage, Event<T>, ValidateUnsigned},
+		OcwDemo: pallet_ocw::{Pallet, Call, Storage, Event<T>, Config<T>, ValidateUnsigned},
 	}
 );
 
@@ -512,6 +567,16 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_ocw_rpc_runtime_api::OcwApi<Block, pallet_ocw::Price> for Runtime {
+		fn latest_price(asset: Vec<u8>) -> Option<pallet_ocw::Price> {
+			<OcwDemo as pallet_ocw::PriceProvider<BlockNumber>>::latest(&asset)
+		}
+
+		fn twap(asset: Vec<u8>, window: u64) -> Option<pallet_ocw::Price> {
+			<OcwDemo as pallet_ocw::PriceProvider<BlockNumber>>::twap(&asset, window)
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn dispatch_benchmark(
@@ -0,0 +1,41 @@
+//! RPC interface for the offchain worker fetch error counts of ocw-http.
+
+use codec::Decode;
+use jsonrpc_core::Result;
+use jsonrpc_derive::rpc;
+use ocw_http::{error_counts_key, FetchErrorCounts};
+use sp_core::offchain::{OffchainStorage, STORAGE_PREFIX};
+
+/// Offchain worker monitoring RPC methods.
+#[rpc]
+pub trait OcwHttpApi {
+	/// The failed fetches of the offchain worker `worker`, e.g. `"ocw-signed"`, counted by this
+	/// node.
+	#[rpc(name = "ocw_fetchErrors")]
+	fn fetch_errors(&self, worker: String) -> Result<FetchErrorCounts>;
+}
+
+/// Implements [`OcwHttpApi`] by reading the node's offchain storage.
+pub struct OcwHttp<S> {
+	storage: S,
+}
+
+impl<S> OcwHttp<S> {
+	/// Create a new `OcwHttp` reading from `storage`.
+	pub fn new(storage: S) -> Self {
+		Self { storage }
+	}
+}
+
+impl<S> OcwHttpApi for OcwHttp<S>
+where
+	S: OffchainStorage + 'static,
+{
+	fn fetch_errors(&self, worker: String) -> Result<FetchErrorCounts> {
+		Ok(self
+			.storage
+			.get(STORAGE_PREFIX, &error_counts_key(worker.as_bytes()))
+			.and_then(|counts| FetchErrorCounts::decode(&mut &counts[..]).ok())
+			.unwrap_or_default())
+	}
+}
@@ -0,0 +1,132 @@
+//! The offchain host functions the client runs on.
+
+use crate::{FetchError, Request, Response};
+use alloc::vec::Vec;
+
+/// The offchain worker host functions the client needs, as provided by `sp_io::offchain`.
+///
+/// Pallets implement it for a unit struct with [`impl_host!`](crate::impl_host), and tests
+/// may implement it by hand to script the network.
+pub trait Host {
+	/// The current time, in milliseconds since the unix epoch.
+	fn timestamp() -> u64;
+
+	/// Block until `timestamp`, in milliseconds since the unix epoch.
+	fn sleep_until(timestamp: u64);
+
+	/// Send `requests` at once and wait for them until `deadline`, in milliseconds since the
+	/// unix epoch, answering in the same order.
+	///
+	/// Responses carry any status; the body is only read for a 200 and is checked against the
+	/// request's [`max_body`](Request::max_body) with [`read_body`](crate::read_body).
+	fn send(requests: &[Request], deadline: u64) -> Vec<Result<Response, FetchError>>;
+
+	/// The value of `key` in persistent local storage.
+	fn local_get(key: &[u8]) -> Option<Vec<u8>>;
+
+	/// Set `key` to `value` in persistent local storage.
+	fn local_set(key: &[u8], value: &[u8]);
+
+	/// Set `key` to `new` in persistent local storage if it is still `old`. Returns whether it
+	/// was set.
+	fn local_compare_and_set(key: &[u8], old: Option<&[u8]>, new: &[u8]) -> bool;
+}
+
+/// Declare a unit struct implementing [`Host`] with the `sp_io` and `sp_runtime` of the
+/// calling crate, which must depend on both.
+///
+/// ```ignore
+/// ocw_http::impl_host! {
+///     /// The offchain host functions of this pallet's worker.
+///     pub struct OffchainHost;
+/// }
+/// ```
+#[macro_export]
+macro_rules! impl_host {
+	($(#[$attr:meta])* $vis:vis struct $name:ident;) => {
+		$(#[$attr])*
+		$vis struct $name;
+
+		impl $crate::Host for $name {
+			fn timestamp() -> u64 {
+				sp_io::offchain::timestamp().unix_millis()
+			}
+
+			fn sleep_until(timestamp: u64) {
+				sp_io::offchain::sleep_until(sp_runtime::offchain::Timestamp::from_unix_millis(timestamp))
+			}
+
+			fn send(
+				requests: &[$crate::Request],
+				deadline: u64,
+			) -> $crate::__private::Vec<Result<$crate::Response, $crate::FetchError>> {
+				use sp_runtime::offchain::{http, Timestamp};
+
+				let deadline = Timestamp::from_unix_millis(deadline);
+				let mut results: $crate::__private::Vec<Result<$crate::Response, $crate::FetchError>> =
+					requests.iter().map(|_| Err($crate::FetchError::Http)).collect();
+
+				// Send all requests first, then wait for them together.
+				let mut sent = $crate::__private::Vec::new();
+				let mut pending = $crate::__private::Vec::new();
+				for (index, request) in requests.iter().enumerate() {
+					let method = match request.method {
+						$crate::Method::Get => http::Method::Get,
+						$crate::Method::Post => http::Method::Post,
+					};
+					let body: $crate::__private::Vec<&[u8]> = if request.body.is_empty() {
+						Default::default()
+					} else {
+						::core::iter::once(&request.body[..]).collect()
+					};
+					let mut builder = http::Request::new(&request.url)
+						.method(method)
+						.body(body)
+						.deadline(deadline);
+					for (name, value) in request.headers.iter() {
+						builder = builder.add_header(name, value);
+					}
+					if let Ok(request) = builder.send() {
+						sent.push(index);
+						pending.push(request);
+					}
+				}
+
+				let responses = http::PendingRequest::try_wait_all(pending, deadline);
+				for (index, response) in sent.into_iter().zip(responses) {
+					results[index] = match response {
+						Ok(Ok(mut response)) => {
+							let headers = response.headers().raw.clone();
+							let body = if response.code == 200 {
+								$crate::read_body(&headers, response.body(), requests[index].max_body)
+							} else {
+								Ok(Default::default())
+							};
+							body.map(|body| $crate::Response { code: response.code, headers, body })
+						}
+						Ok(Err(http::Error::DeadlineReached)) | Err(_) => Err($crate::FetchError::Deadline),
+						Ok(Err(_)) => Err($crate::FetchError::Http),
+					};
+				}
+				results
+			}
+
+			fn local_get(key: &[u8]) -> Option<$crate::__private::Vec<u8>> {
+				sp_io::offchain::local_storage_get(sp_runtime::offchain::StorageKind::PERSISTENT, key)
+			}
+
+			fn local_set(key: &[u8], value: &[u8]) {
+				sp_io::offchain::local_storage_set(sp_runtime::offchain::StorageKind::PERSISTENT, key, value)
+			}
+
+			fn local_compare_and_set(key: &[u8], old: Option<&[u8]>, new: &[u8]) -> bool {
+				sp_io::offchain::local_storage_compare_and_set(
+					sp_runtime::offchain::StorageKind::PERSISTENT,
+					key,
+					old.map(|old| old.to_vec()),
+					new,
+				)
+			}
+		}
+	};
+}
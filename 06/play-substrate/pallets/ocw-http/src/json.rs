@@ -0,0 +1,81 @@
+//! Reading JSON responses: typed decoding, values at a JSON pointer, and deserializers for
+//! fields whose JSON type differs from the Rust one.
+
+use crate::FetchError;
+use alloc::{string::String, vec::Vec};
+use core::str;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// Decode the JSON `body` into `T`.
+pub fn decode<'a, T: Deserialize<'a>>(body: &'a [u8]) -> Result<T, FetchError> {
+	let text = str::from_utf8(body).map_err(|_| FetchError::Utf8)?;
+	serde_json::from_str(text).map_err(|_| FetchError::Json)
+}
+
+/// The JSON value found at the RFC 6901 `pointer`, such as `/data/price`, in `body`.
+pub fn pointer(body: &[u8], pointer: &[u8]) -> Option<Value> {
+	let mut value: Value = serde_json::from_slice(body).ok()?;
+	let pointer = str::from_utf8(pointer).ok()?;
+	Some(value.pointer_mut(pointer)?.take())
+}
+
+/// The text of a JSON string or number, to parse with [`crate::number`].
+pub fn decimal_text(value: &Value) -> Option<Vec<u8>> {
+	match value {
+		Value::String(text) => Some(text.as_bytes().to_vec()),
+		// Keep the number's textual form rather than going through a float.
+		Value::Number(_) => serde_json::to_vec(value).ok(),
+		_ => None,
+	}
+}
+
+/// Deserialize a JSON string into its bytes, e.g. with
+/// `#[serde(deserialize_with = "ocw_http::de_string_to_bytes")]`.
+pub fn de_string_to_bytes<'de, D>(de: D) -> Result<Vec<u8>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	let s: String = Deserialize::deserialize(de)?;
+	Ok(s.into_bytes())
+}
+
+/// Deserialize a JSON number into an integer, dropping the fraction.
+pub fn de_float_to_integer<'de, D>(de: D) -> Result<u32, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	let f: f64 = Deserialize::deserialize(de)?;
+	Ok(f as u32)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Deserialize, PartialEq, Debug)]
+	struct Info {
+		#[serde(deserialize_with = "de_string_to_bytes")]
+		login: Vec<u8>,
+		#[serde(deserialize_with = "de_float_to_integer")]
+		price: u32,
+	}
+
+	#[test]
+	fn decodes_with_the_deserializers() {
+		let body = br#"{"login":"a\"b","price":6.99}"#;
+		assert_eq!(decode(body), Ok(Info { login: b"a\"b".to_vec(), price: 6 }));
+		assert_eq!(decode::<Info>(br#"{"login":1,"price":6}"#), Err(FetchError::Json));
+	}
+
+	#[test]
+	fn reads_decimals_at_a_pointer() {
+		let body = br#"{"data":{"priceUsd":"6.1","volume":12.5,"id":null}}"#;
+		let text = |path: &[u8]| pointer(body, path).and_then(|value| decimal_text(&value));
+		assert_eq!(text(b"/data/priceUsd"), Some(b"6.1".to_vec()));
+		assert_eq!(text(b"/data/volume"), Some(b"12.5".to_vec()));
+		assert_eq!(text(b"/data/id"), None);
+		assert_eq!(text(b"/data/missing"), None);
+		assert_eq!(pointer(b"not json", b""), None);
+	}
+}
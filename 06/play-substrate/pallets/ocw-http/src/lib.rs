@@ -0,0 +1,177 @@
+//! Offchain worker HTTP client shared by the ocw pallets.
+//!
+//! A [`Request`] is built with its headers, deadline, retries and response size limit, sent
+//! with [`Request::send`] or, several at once, with [`send_all`], and its [`Response`] decoded
+//! into a typed struct with [`Response::json`]. The [`json`] and [`number`] modules read
+//! values out of responses without going through floats.
+//!
+//! The crate does not depend on Substrate, so pallets on different Substrate versions share it:
+//! each binds it to its own host functions with [`impl_host!`].
+//!
+//! Fetch failures are typed by [`FetchError`], and every worker counts its failures in
+//! persistent offchain storage under [`error_counts_key`], where the `ocw_fetchErrors` RPC of
+//! `ocw-http-rpc` reads them for monitoring.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod host;
+pub mod json;
+pub mod number;
+mod request;
+
+#[cfg(test)]
+mod mock;
+
+pub use host::Host;
+pub use json::{de_float_to_integer, de_string_to_bytes};
+pub use request::{read_body, send_all, Method, Request, Response, DEFAULT_TIMEOUT};
+
+use alloc::vec::Vec;
+use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+/// Used by [`impl_host!`], which expands in crates that may not import `alloc`.
+#[doc(hidden)]
+pub mod __private {
+	pub use alloc::vec::Vec;
+}
+
+const ERROR_COUNTS_KEY_PREFIX: &[u8] = b"ocw-http::fetch-errors::";
+
+/// Why fetching from a url failed.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FetchError {
+	/// The request could not be sent or its response read.
+	Http,
+	/// The deadline passed before the response arrived.
+	Deadline,
+	/// The server answered with a status other than 200.
+	Status(u16),
+	/// The response body is not UTF-8.
+	Utf8,
+	/// The response body is not the expected JSON.
+	Json,
+	/// The response body is larger than allowed.
+	TooLarge,
+}
+
+impl FetchError {
+	/// Whether trying again later may succeed: the server was unreachable, busy (429) or
+	/// failing (5xx).
+	pub fn is_transient(&self) -> bool {
+		match self {
+			FetchError::Http => true,
+			FetchError::Status(code) => *code == 429 || *code >= 500,
+			_ => false,
+		}
+	}
+}
+
+/// How many fetches of a worker failed, by [`FetchError`] kind.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+#[derive(Encode, Decode, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct FetchErrorCounts {
+	pub http: u32,
+	pub deadline: u32,
+	pub status: u32,
+	pub utf8: u32,
+	pub json: u32,
+	/// The latest status other than 200, if any.
+	pub last_status: Option<u16>,
+	pub too_large: u32,
+}
+
+impl FetchErrorCounts {
+	/// Count `error`.
+	pub fn record(&mut self, error: &FetchError) {
+		let count = match error {
+			FetchError::Http => &mut self.http,
+			FetchError::Deadline => &mut self.deadline,
+			FetchError::Status(code) => {
+				self.last_status = Some(*code);
+				&mut self.status
+			}
+			FetchError::Utf8 => &mut self.utf8,
+			FetchError::Json => &mut self.json,
+			FetchError::TooLarge => &mut self.too_large,
+		};
+		*count = count.saturating_add(1);
+	}
+
+	/// The number of failed fetches.
+	pub fn total(&self) -> u32 {
+		self.http
+			.saturating_add(self.deadline)
+			.saturating_add(self.status)
+			.saturating_add(self.utf8)
+			.saturating_add(self.json)
+			.saturating_add(self.too_large)
+	}
+}
+
+/// The persistent offchain storage key of the [`FetchErrorCounts`] of `worker`.
+pub fn error_counts_key(worker: &[u8]) -> Vec<u8> {
+	let mut key = ERROR_COUNTS_KEY_PREFIX.to_vec();
+	key.extend_from_slice(worker);
+	key
+}
+
+/// Count a fetch of `worker` failing with `error`.
+pub fn record_error<H: Host>(worker: &[u8], error: &FetchError) {
+	let key = error_counts_key(worker);
+	let old = H::local_get(&key);
+	let mut counts = old
+		.as_ref()
+		.and_then(|counts| FetchErrorCounts::decode(&mut &counts[..]).ok())
+		.unwrap_or_default();
+	counts.record(error);
+	// Should another worker count at the same time, this failure goes uncounted.
+	let _ = H::local_compare_and_set(&key, old.as_deref(), &counts.encode());
+}
+
+/// The failed fetches of `worker` counted so far.
+pub fn error_counts<H: Host>(worker: &[u8]) -> FetchErrorCounts {
+	H::local_get(&error_counts_key(worker))
+		.and_then(|counts| FetchErrorCounts::decode(&mut &counts[..]).ok())
+		.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::TestHost;
+
+	#[test]
+	fn errors_are_counted_per_worker_and_kind() {
+		TestHost::reset();
+		record_error::<TestHost>(b"a", &FetchError::Json);
+		record_error::<TestHost>(b"a", &FetchError::Status(503));
+		record_error::<TestHost>(b"a", &FetchError::Json);
+		record_error::<TestHost>(b"a", &FetchError::TooLarge);
+		record_error::<TestHost>(b"b", &FetchError::Deadline);
+
+		let counts = error_counts::<TestHost>(b"a");
+		assert_eq!(counts.json, 2);
+		assert_eq!(counts.status, 1);
+		assert_eq!(counts.too_large, 1);
+		assert_eq!(counts.last_status, Some(503));
+		assert_eq!(counts.total(), 4);
+		assert_eq!(error_counts::<TestHost>(b"b").total(), 1);
+		assert_eq!(error_counts::<TestHost>(b"c"), FetchErrorCounts::default());
+	}
+
+	#[test]
+	fn transient_errors() {
+		assert!(FetchError::Http.is_transient());
+		assert!(FetchError::Status(429).is_transient());
+		assert!(FetchError::Status(503).is_transient());
+		assert!(!FetchError::Status(404).is_transient());
+		assert!(!FetchError::Deadline.is_transient());
+		assert!(!FetchError::TooLarge.is_transient());
+		assert!(!FetchError::Json.is_transient());
+	}
+}
@@ -0,0 +1,103 @@
+//! A scripted [`Host`] for the tests of this crate.
+
+use crate::{read_body, FetchError, Host, Request, Response};
+use std::{cell::RefCell, collections::BTreeMap, collections::VecDeque};
+
+#[derive(Default)]
+struct State {
+	now: u64,
+	deadline: u64,
+	responses: VecDeque<Result<Response, FetchError>>,
+	sent: Vec<Request>,
+	storage: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+thread_local! {
+	static STATE: RefCell<State> = RefCell::new(State::default());
+}
+
+/// Answers requests with the responses queued by [`TestHost::respond`], in order, and keeps
+/// local storage in memory. Time only passes in `sleep_until`.
+pub struct TestHost;
+
+impl TestHost {
+	/// Start over at time 0, with nothing queued, sent or stored.
+	pub fn reset() {
+		STATE.with(|state| *state.borrow_mut() = State::default());
+	}
+
+	/// Answer the next request with `response`.
+	pub fn respond(response: Result<Response, FetchError>) {
+		STATE.with(|state| state.borrow_mut().responses.push_back(response));
+	}
+
+	/// The requests sent so far.
+	pub fn sent() -> Vec<Request> {
+		STATE.with(|state| state.borrow().sent.clone())
+	}
+
+	/// The deadline of the latest requests.
+	pub fn deadline() -> u64 {
+		STATE.with(|state| state.borrow().deadline)
+	}
+}
+
+/// A 200 response with `body`.
+pub fn ok(body: &[u8]) -> Result<Response, FetchError> {
+	Ok(Response { code: 200, headers: Vec::new(), body: body.to_vec() })
+}
+
+impl Host for TestHost {
+	fn timestamp() -> u64 {
+		STATE.with(|state| state.borrow().now)
+	}
+
+	fn sleep_until(timestamp: u64) {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+			state.now = state.now.max(timestamp);
+		})
+	}
+
+	fn send(requests: &[Request], deadline: u64) -> Vec<Result<Response, FetchError>> {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+			state.deadline = deadline;
+			requests
+				.iter()
+				.map(|request| {
+					state.sent.push(request.clone());
+					match state.responses.pop_front().expect("no response queued") {
+						Ok(response) if response.code == 200 => {
+							let body = response.body.iter().copied();
+							read_body(&response.headers, body, request.max_body)
+								.map(|body| Response { body, ..response.clone() })
+						}
+						response => response,
+					}
+				})
+				.collect()
+		})
+	}
+
+	fn local_get(key: &[u8]) -> Option<Vec<u8>> {
+		STATE.with(|state| state.borrow().storage.get(key).cloned())
+	}
+
+	fn local_set(key: &[u8], value: &[u8]) {
+		STATE.with(|state| {
+			state.borrow_mut().storage.insert(key.to_vec(), value.to_vec());
+		})
+	}
+
+	fn local_compare_and_set(key: &[u8], old: Option<&[u8]>, new: &[u8]) -> bool {
+		STATE.with(|state| {
+			let mut state = state.borrow_mut();
+			if state.storage.get(key).map(|value| &value[..]) != old {
+				return false;
+			}
+			state.storage.insert(key.to_vec(), new.to_vec());
+			true
+		})
+	}
+}
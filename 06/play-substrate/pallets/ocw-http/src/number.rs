@@ -0,0 +1,131 @@
+//! Parsing decimal text, as found in JSON responses, into fixed point integers without going
+//! through floats.
+
+use core::convert::TryFrom;
+
+/// Parse a decimal string into an integer with `decimals` digits of precision, e.g.
+/// `parse_decimal("6.5", 2) == Some(650)`.
+///
+/// Accepts an optional leading `+`, an integer part, a fractional part or both (`"6"`,
+/// `"6.5"`, `".5"`, `"6."`), and an optional exponent (`"1.5e-7"`, `"2E+3"`). Digits beyond
+/// the precision are truncated. Returns `None` for negative, empty or malformed input and for
+/// values that do not fit.
+pub fn parse_decimal(text: &str, decimals: u32) -> Option<u128> {
+	let bytes = text.trim().as_bytes();
+	let bytes = bytes.strip_prefix(b"+").unwrap_or(bytes);
+
+	let (mantissa_part, exponent_part) =
+		match bytes.iter().position(|b| *b == b'e' || *b == b'E') {
+			Some(index) => (&bytes[..index], Some(&bytes[index + 1..])),
+			None => (bytes, None),
+		};
+
+	// `value = mantissa * 10^scale`
+	let mut mantissa: u128 = 0;
+	let mut scale: i32 = 0;
+	let mut digits = 0;
+	let mut seen_dot = false;
+	for byte in mantissa_part {
+		match byte {
+			b'.' if !seen_dot => seen_dot = true,
+			b'0'..=b'9' => {
+				digits += 1;
+				let digit = (byte - b'0') as u128;
+				match mantissa.checked_mul(10).and_then(|m| m.checked_add(digit)) {
+					Some(next) => {
+						mantissa = next;
+						if seen_dot {
+							scale -= 1;
+						}
+					}
+					// Out of room: fractional digits are dropped, integer digits still count.
+					None if !seen_dot => scale = scale.checked_add(1)?,
+					None => {}
+				}
+			}
+			_ => return None,
+		}
+	}
+	if digits == 0 {
+		return None;
+	}
+
+	if let Some(exponent) = exponent_part {
+		scale = scale.checked_add(parse_exponent(exponent)?)?;
+	}
+
+	let shift = scale.checked_add(i32::try_from(decimals).ok()?)?;
+	if shift >= 0 {
+		mantissa.checked_mul(10u128.checked_pow(shift as u32)?)
+	} else {
+		match 10u128.checked_pow(shift.unsigned_abs()) {
+			Some(divisor) => Some(mantissa / divisor),
+			// Smaller than the precision.
+			None => Some(0),
+		}
+	}
+}
+
+/// Parse a decimal string that may be negative, such as a temperature, with the same rules as
+/// [`parse_decimal`].
+pub fn parse_signed_decimal(text: &str, decimals: u32) -> Option<i128> {
+	let text = text.trim();
+	let (negative, magnitude) = match text.strip_prefix('-') {
+		Some(magnitude) => (true, magnitude),
+		None => (false, text),
+	};
+	let value = i128::try_from(parse_decimal(magnitude, decimals)?).ok()?;
+	Some(if negative { -value } else { value })
+}
+
+fn parse_exponent(exponent: &[u8]) -> Option<i32> {
+	let (negative, digits) = match exponent.split_first()? {
+		(b'-', rest) => (true, rest),
+		(b'+', rest) => (false, rest),
+		_ => (false, exponent),
+	};
+	if digits.is_empty() {
+		return None;
+	}
+	let mut value: i32 = 0;
+	for byte in digits {
+		if !byte.is_ascii_digit() {
+			return None;
+		}
+		value = value.checked_mul(10)?.checked_add((byte - b'0') as i32)?;
+	}
+	Some(if negative { -value } else { value })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_to_the_given_precision() {
+		assert_eq!(parse_decimal("6.5", 2), Some(650));
+		assert_eq!(parse_decimal("6.509", 2), Some(650));
+		assert_eq!(parse_decimal("48123.45", 0), Some(48123));
+		assert_eq!(parse_decimal("1.5e-7", 9), Some(150));
+		assert_eq!(parse_decimal("2E+3", 0), Some(2_000));
+		assert_eq!(parse_decimal(".5", 1), Some(5));
+		assert_eq!(parse_decimal("1e-400", 18), Some(0));
+	}
+
+	#[test]
+	fn rejects_malformed_or_oversized_input() {
+		for input in ["", ".", "-1", "6.5.1", "6,5", "1e", "NaN", "0x10"].iter() {
+			assert_eq!(parse_decimal(input, 2), None, "{:?} should be rejected", input);
+		}
+		assert_eq!(parse_decimal("1e39", 0), None);
+		assert_eq!(parse_decimal("1", u32::MAX), None);
+	}
+
+	#[test]
+	fn parses_signed_decimals() {
+		assert_eq!(parse_signed_decimal("-2.5", 1), Some(-25));
+		assert_eq!(parse_signed_decimal(" 2.5 ", 1), Some(25));
+		assert_eq!(parse_signed_decimal("--1", 1), None);
+		assert_eq!(parse_signed_decimal("340282366920938463463374607431768211455", 0), None);
+	}
+}
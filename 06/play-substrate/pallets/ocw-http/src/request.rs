@@ -0,0 +1,367 @@
+//! The request builder: deadlines, retries with backoff, a response size limit, and `ETag`
+//! caching in offchain local storage.
+
+use crate::{json, FetchError, Host};
+use alloc::{
+	string::{String, ToString},
+	vec::Vec,
+};
+use codec::{Decode, Encode};
+use core::str;
+use serde::{de::DeserializeOwned, Deserialize};
+
+/// Milliseconds a [`Request`] may take, retries included, unless set with
+/// [`Request::timeout`].
+pub const DEFAULT_TIMEOUT: u64 = 3_000;
+
+const ETAG_KEY_PREFIX: &[u8] = b"ocw-http::etag::";
+
+/// The HTTP method of a [`Request`].
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Method {
+	Get,
+	Post,
+}
+
+/// An HTTP request and how hard to try sending it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Request {
+	pub method: Method,
+	pub url: String,
+	pub headers: Vec<(String, String)>,
+	pub body: Vec<u8>,
+	/// Milliseconds from sending until the deadline, retries included.
+	pub timeout: u64,
+	/// The number of times a transient failure is retried, see [`FetchError::is_transient`].
+	pub retries: u32,
+	/// Milliseconds to wait before the first retry, doubled for each one after.
+	pub backoff: u64,
+	/// The largest response body accepted, in bytes.
+	pub max_body: Option<u32>,
+	/// Whether responses are cached by their `ETag`.
+	pub cache_etag: bool,
+}
+
+impl Request {
+	/// A request with `method` to `url`, without headers, body, retries or size limit.
+	pub fn new(method: Method, url: &str) -> Self {
+		Request {
+			method,
+			url: url.to_string(),
+			headers: Vec::new(),
+			body: Vec::new(),
+			timeout: DEFAULT_TIMEOUT,
+			retries: 0,
+			backoff: 0,
+			max_body: None,
+			cache_etag: false,
+		}
+	}
+
+	/// A GET request to `url`.
+	pub fn get(url: &str) -> Self {
+		Self::new(Method::Get, url)
+	}
+
+	/// A POST request of `body` to `url`.
+	pub fn post(url: &str, body: Vec<u8>) -> Self {
+		Request { body, ..Self::new(Method::Post, url) }
+	}
+
+	/// Add the header `name: value`.
+	pub fn header(mut self, name: &str, value: &str) -> Self {
+		self.headers.push((name.to_string(), value.to_string()));
+		self
+	}
+
+	/// Give up `timeout` milliseconds after sending, retries included.
+	pub fn timeout(mut self, timeout: u64) -> Self {
+		self.timeout = timeout;
+		self
+	}
+
+	/// Retry transient failures up to `retries` times, waiting `backoff` milliseconds before
+	/// the first retry and twice as long before each one after. Only retries that can start
+	/// before the deadline are made.
+	pub fn retries(mut self, retries: u32, backoff: u64) -> Self {
+		self.retries = retries;
+		self.backoff = backoff;
+		self
+	}
+
+	/// Reject response bodies larger than `max_body` bytes.
+	pub fn max_body(mut self, max_body: u32) -> Self {
+		self.max_body = Some(max_body);
+		self
+	}
+
+	/// Cache responses carrying an `ETag` in local storage, and send it as `If-None-Match`
+	/// later, so a `304 Not Modified` answer comes with the cached body.
+	pub fn cache_etag(mut self) -> Self {
+		self.cache_etag = true;
+		self
+	}
+
+	/// Send the request, retrying as configured. Any status other than 200, or 304 for a
+	/// cached response, is a [`FetchError::Status`].
+	pub fn send<H: Host>(&self) -> Result<Response, FetchError> {
+		let deadline = H::timestamp().saturating_add(self.timeout);
+		let key = etag_key(&self.url);
+		let cached = if self.cache_etag {
+			H::local_get(&key).and_then(|cached| Cached::decode(&mut &cached[..]).ok())
+		} else {
+			None
+		};
+		let etag = cached.as_ref().and_then(|cached| str::from_utf8(&cached.etag).ok());
+		let request = match etag {
+			Some(etag) => self.clone().header("If-None-Match", etag),
+			None => self.clone(),
+		};
+
+		let mut backoff = self.backoff;
+		let mut retries = 0;
+		loop {
+			let result = H::send(core::slice::from_ref(&request), deadline)
+				.pop()
+				.unwrap_or(Err(FetchError::Http));
+			let result = match (result, &cached) {
+				(Ok(response), Some(cached)) if response.code == 304 =>
+					return Ok(Response { body: cached.body.clone(), ..response }),
+				(Ok(response), _) if response.code == 200 => {
+					if let (true, Some(etag)) = (self.cache_etag, response.header("ETag")) {
+						let cached = Cached { etag: etag.as_bytes().to_vec(), body: response.body.clone() };
+						H::local_set(&key, &cached.encode());
+					}
+					return Ok(response);
+				}
+				(Ok(response), _) => Err(FetchError::Status(response.code)),
+				(Err(error), _) => Err(error),
+			};
+			match result {
+				Err(error) if error.is_transient() && retries < self.retries => {
+					let retry_at = H::timestamp().saturating_add(backoff);
+					if retry_at >= deadline {
+						return Err(error);
+					}
+					log::warn!("request to {} failed with {:?}, retrying in {} ms", self.url, error, backoff);
+					H::sleep_until(retry_at);
+					backoff = backoff.saturating_mul(2);
+					retries += 1;
+				}
+				result => return result,
+			}
+		}
+	}
+
+	/// Send the request and decode its JSON response into `T`.
+	pub fn fetch_json<H: Host, T: DeserializeOwned>(&self) -> Result<T, FetchError> {
+		self.send::<H>()?.json()
+	}
+}
+
+/// Send `requests` at once, without retries or `ETag` caching, and wait for them until the
+/// longest of their timeouts. Any status other than 200 is a [`FetchError::Status`].
+pub fn send_all<H: Host>(requests: &[Request]) -> Vec<Result<Response, FetchError>> {
+	let timeout = requests.iter().map(|request| request.timeout).max().unwrap_or_default();
+	let deadline = H::timestamp().saturating_add(timeout);
+	H::send(requests, deadline)
+		.into_iter()
+		.map(|result| match result {
+			Ok(response) if response.code != 200 => Err(FetchError::Status(response.code)),
+			result => result,
+		})
+		.collect()
+}
+
+/// A response to a [`Request`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Response {
+	pub code: u16,
+	pub headers: Vec<(Vec<u8>, Vec<u8>)>,
+	pub body: Vec<u8>,
+}
+
+impl Response {
+	/// The value of the header `name`, compared case-insensitively.
+	pub fn header(&self, name: &str) -> Option<&str> {
+		header(&self.headers, name)
+	}
+
+	/// The body as text.
+	pub fn text(&self) -> Result<&str, FetchError> {
+		str::from_utf8(&self.body).map_err(|_| FetchError::Utf8)
+	}
+
+	/// Decode the JSON body into `T`.
+	pub fn json<'a, T: Deserialize<'a>>(&'a self) -> Result<T, FetchError> {
+		json::decode(&self.body)
+	}
+}
+
+/// Read a response `body` with `headers`, unless it is larger than `max_body` bytes.
+///
+/// A `Content-Length` over the limit is rejected before anything is read, and reading stops
+/// one byte past the limit otherwise.
+pub fn read_body(
+	headers: &[(Vec<u8>, Vec<u8>)],
+	body: impl Iterator<Item = u8>,
+	max_body: Option<u32>,
+) -> Result<Vec<u8>, FetchError> {
+	let max_body = match max_body {
+		Some(max_body) => max_body as usize,
+		None => return Ok(body.collect()),
+	};
+	let length = header(headers, "Content-Length").and_then(|length| length.parse::<usize>().ok());
+	if matches!(length, Some(length) if length > max_body) {
+		return Err(FetchError::TooLarge);
+	}
+	let body: Vec<u8> = body.take(max_body + 1).collect();
+	if body.len() > max_body {
+		return Err(FetchError::TooLarge);
+	}
+	Ok(body)
+}
+
+/// The `ETag` of the last response from a url and its body.
+#[derive(Encode, Decode)]
+struct Cached {
+	etag: Vec<u8>,
+	body: Vec<u8>,
+}
+
+fn etag_key(url: &str) -> Vec<u8> {
+	let mut key = ETAG_KEY_PREFIX.to_vec();
+	key.extend_from_slice(url.as_bytes());
+	key
+}
+
+fn header<'a>(headers: &'a [(Vec<u8>, Vec<u8>)], name: &str) -> Option<&'a str> {
+	headers
+		.iter()
+		.find(|(key, _)| key.eq_ignore_ascii_case(name.as_bytes()))
+		.and_then(|(_, value)| str::from_utf8(value).ok())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{ok, TestHost};
+
+	const URL: &str = "https://example.com/price";
+
+	#[test]
+	fn sends_the_built_request() {
+		TestHost::reset();
+		TestHost::respond(ok(b"{}"));
+		let request = Request::post(URL, b"query".to_vec()).header("User-Agent", "ocw");
+
+		assert_eq!(request.send::<TestHost>().map(|response| response.body), Ok(b"{}".to_vec()));
+		let sent = TestHost::sent();
+		assert_eq!(sent.len(), 1);
+		assert_eq!(sent[0].method, Method::Post);
+		assert_eq!(sent[0].body, b"query".to_vec());
+		assert_eq!(sent[0].headers, vec![("User-Agent".to_string(), "ocw".to_string())]);
+	}
+
+	#[test]
+	fn retries_transient_failures_with_backoff() {
+		TestHost::reset();
+		TestHost::respond(Err(FetchError::Http));
+		TestHost::respond(ok(b"").map(|response| Response { code: 503, ..response }));
+		TestHost::respond(ok(b"{}"));
+
+		let response = Request::get(URL).retries(2, 100).send::<TestHost>();
+		assert_eq!(response.map(|response| response.body), Ok(b"{}".to_vec()));
+		// Backoffs of 100ms and 200ms.
+		assert_eq!(TestHost::timestamp(), 300);
+		assert_eq!(TestHost::sent().len(), 3);
+	}
+
+	#[test]
+	fn gives_up_after_the_last_retry_or_at_the_deadline() {
+		TestHost::reset();
+		for _ in 0..3 {
+			TestHost::respond(Err(FetchError::Http));
+		}
+		assert_eq!(Request::get(URL).retries(1, 100).send::<TestHost>(), Err(FetchError::Http));
+		assert_eq!(TestHost::sent().len(), 2);
+
+		TestHost::reset();
+		for _ in 0..3 {
+			TestHost::respond(Err(FetchError::Http));
+		}
+		// The second retry would start after the deadline.
+		let request = Request::get(URL).retries(5, 100).timeout(250);
+		assert_eq!(request.send::<TestHost>(), Err(FetchError::Http));
+		assert_eq!(TestHost::sent().len(), 2);
+	}
+
+	#[test]
+	fn does_not_retry_other_failures() {
+		TestHost::reset();
+		TestHost::respond(ok(b"").map(|response| Response { code: 404, ..response }));
+		let request = Request::get(URL).retries(3, 100);
+		assert_eq!(request.send::<TestHost>(), Err(FetchError::Status(404)));
+		assert_eq!(TestHost::sent().len(), 1);
+	}
+
+	#[test]
+	fn unchanged_responses_are_served_from_the_etag_cache() {
+		TestHost::reset();
+		let mut first = ok(b"{\"v\":1}").unwrap();
+		first.headers.push((b"ETag".to_vec(), b"\"v1\"".to_vec()));
+		TestHost::respond(Ok(first));
+		TestHost::respond(ok(b"").map(|response| Response { code: 304, ..response }));
+		let request = Request::get(URL).cache_etag();
+
+		assert_eq!(request.send::<TestHost>().map(|response| response.body), Ok(b"{\"v\":1}".to_vec()));
+		assert_eq!(request.send::<TestHost>().map(|response| response.body), Ok(b"{\"v\":1}".to_vec()));
+		let sent = TestHost::sent();
+		assert!(sent[0].headers.is_empty());
+		assert_eq!(sent[1].headers, vec![("If-None-Match".to_string(), "\"v1\"".to_string())]);
+	}
+
+	#[test]
+	fn send_all_answers_in_order() {
+		TestHost::reset();
+		TestHost::respond(ok(b"a"));
+		TestHost::respond(ok(b"").map(|response| Response { code: 500, ..response }));
+		TestHost::respond(Err(FetchError::Deadline));
+		let requests = vec![Request::get(URL), Request::get(URL), Request::get(URL).timeout(5_000)];
+
+		let responses: Vec<_> = send_all::<TestHost>(&requests)
+			.into_iter()
+			.map(|result| result.map(|response| response.body))
+			.collect();
+		assert_eq!(responses, vec![Ok(b"a".to_vec()), Err(FetchError::Status(500)), Err(FetchError::Deadline)]);
+		assert_eq!(TestHost::deadline(), 5_000);
+	}
+
+	#[test]
+	fn bodies_over_the_limit_are_rejected() {
+		let length = vec![(b"content-length".to_vec(), b"11".to_vec())];
+		assert_eq!(read_body(&length, b"tiny".iter().copied(), Some(10)), Err(FetchError::TooLarge));
+		assert_eq!(read_body(&[], [b' '; 11].iter().copied(), Some(10)), Err(FetchError::TooLarge));
+		assert_eq!(read_body(&[], [b' '; 10].iter().copied(), Some(10)), Ok(vec![b' '; 10]));
+		assert_eq!(read_body(&[], [b' '; 11].iter().copied(), None), Ok(vec![b' '; 11]));
+	}
+
+	#[test]
+	fn decodes_json_into_typed_structs() {
+		#[derive(Deserialize, PartialEq, Debug)]
+		struct Price {
+			#[serde(rename = "USD", deserialize_with = "crate::de_float_to_integer")]
+			usd: u32,
+		}
+
+		TestHost::reset();
+		TestHost::respond(ok(br#"{"USD":48123.45}"#));
+		TestHost::respond(ok(b"[1,"));
+		TestHost::respond(ok(&[0xff, 0xfe]));
+		let request = Request::get(URL);
+
+		assert_eq!(request.fetch_json::<TestHost, Price>(), Ok(Price { usd: 48123 }));
+		assert_eq!(request.fetch_json::<TestHost, Price>(), Err(FetchError::Json));
+		assert_eq!(request.fetch_json::<TestHost, Price>(), Err(FetchError::Utf8));
+	}
+}
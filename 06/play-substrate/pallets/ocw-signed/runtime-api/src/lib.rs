@@ -0,0 +1,14 @@
+//! Runtime API definition for the prices of pallet-ocw-signed.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+sp_api::decl_runtime_apis! {
+	/// Read access to the prices submitted by the offchain workers.
+	pub trait PriceApi {
+		/// The mean of the recent prices.
+		fn average_price() -> Option<u32>;
+		/// The time-weighted average of the recent prices, weighted by the blocks each one
+		/// stayed the latest.
+		fn twap() -> Option<u32>;
+	}
+}
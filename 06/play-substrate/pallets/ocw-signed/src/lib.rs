@@ -1,38 +1,13 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-/// A module for offchain worker send unsigned transaction
-
-use frame_support::{
-	debug,
-	decl_module,
-	decl_storage,
-	decl_event,
-	decl_error,
-	dispatch::{DispatchResult},
-};
-use frame_system::{
-	self as system,
-	ensure_signed,
-	offchain::{
-		Signer,
-		CreateSignedTransaction,
-		SendSignedTransaction,
-		AppCrypto,
-	},
-};
-use sp_core::crypto::KeyTypeId;
-use sp_std::vec::Vec;
-use sp_runtime::{
-		offchain::{
-			http,
-			Duration,
-		},
-};
-use sp_std::prelude::*;
-// We use `alt_serde`, and Xanewok-modified `serde_json` so that we can compile the program
-//   with serde(features `std`) and alt_serde(features `no_std`).
-use alt_serde::{Deserialize, Deserializer};
-use codec::{Encode, Decode};
+/// A module for offchain worker send signed transaction
+
+pub use pallet::*;
+
+ocw_http::impl_host! {
+	/// The offchain host functions the worker fetches prices with.
+	pub struct OffchainHost;
+}
 
 #[cfg(test)]
 mod mock;
@@ -40,26 +15,6 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
-// TODO
-// type TokenPrice = u32;
-
-const MAX_LEN: usize = 64; // TODO configurage
-
-// Specifying serde path as `alt_serde`
-// ref: https://serde.rs/container-attrs.html#crate
-#[serde(crate = "alt_serde")]
-#[derive(Deserialize, Encode, Decode, Default)]
-struct PriceInfo {
-	#[serde(rename(deserialize = "USD"), deserialize_with = "de_float_to_integer")]
-    usd: u32,
-}
-
-pub fn de_float_to_integer<'de, D>(de: D) -> Result<u32, D::Error>
-where D: Deserializer<'de> {
-	let f: f32 = Deserialize::deserialize(de)?;
-	Ok(f as u32)
-}
-
 /// Defines application identifier for crypto keys of this module.
 ///
 /// Every module that deals with signatures needs to declare its unique identifier for
@@ -67,7 +22,8 @@ where D: Deserializer<'de> {
 /// When offchain worker is signing transactions it's going to request keys of type
 /// `KeyTypeId` from the keystore and use the ones it finds to sign the transaction.
 /// The keys can be inserted manually via RPC (see `author_insertKey`).
-pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"btc!");
+pub const KEY_TYPE: sp_core::crypto::KeyTypeId = sp_core::crypto::KeyTypeId(*b"btc!");
+
 /// Based on the above `KeyTypeId` we need to generate a pallet-specific crypto type wrappers.
 /// We can use from supported crypto kinds (`sr25519`, `ed25519` and `ecdsa`) and augment
 /// the types with this pallet-specific identifier.
@@ -78,168 +34,296 @@ pub mod sr25519 {
 	app_crypto!(sr25519, KEY_TYPE);
 
 	pub type AuthorityId = Public;
-}
 
-/// The pallet's configuration trait.
-pub trait Trait: system::Trait + CreateSignedTransaction<Call<Self>> {
-	/// The identifier type for an offchain worker.
-	type AuthorityId: AppCrypto<Self::Public, Self::Signature>;
+	/// Signs the offchain worker's transactions with the `KEY_TYPE` sr25519 keys.
+	pub struct AuthId;
 
-	/// The overarching event type.
-	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	impl frame_system::offchain::AppCrypto<sp_runtime::MultiSigner, sp_runtime::MultiSignature> for AuthId {
+		type RuntimeAppPublic = Public;
+		type GenericSignature = sp_core::sr25519::Signature;
+		type GenericPublic = sp_core::sr25519::Public;
+	}
 
-	/// The overarching dispatch call type.
-	type Call: From<Call<Self>>;
-	
+	// Implemented for the mock runtime, whose accounts are plain sr25519 keys.
+	impl frame_system::offchain::AppCrypto<sp_core::sr25519::Public, sp_core::sr25519::Signature> for AuthId {
+		type RuntimeAppPublic = Public;
+		type GenericSignature = sp_core::sr25519::Signature;
+		type GenericPublic = sp_core::sr25519::Public;
+	}
 }
 
-// This pallet's storage items.
-decl_storage! {
-	trait Store for Module<T: Trait> as OcwSignedModule {
-		/// A vector of recently submitted prices.
-		/// 
-		/// This is used to calculate average price, should have bounded size.
-		Prices get(fn prices): Vec<u32>;
+#[frame_support::pallet]
+pub mod pallet {
+	use codec::{Decode, Encode};
+	use frame_support::{
+		dispatch::DispatchResultWithPostInfo,
+		pallet_prelude::*,
+		traits::{GetPalletVersion, PalletInfo, PalletVersion},
+	};
+	use frame_system::{
+		offchain::{AppCrypto, CreateSignedTransaction, SendSignedTransaction, Signer},
+		pallet_prelude::*,
+	};
+	use crate::OffchainHost;
+	use ocw_http::{FetchError, Request};
+	use serde::Deserialize;
+	use sp_runtime::{
+		traits::{Saturating, UniqueSaturatedInto, Zero},
+		RuntimeDebug,
+	};
+	use sp_std::prelude::*;
+
+	// TODO
+	// type TokenPrice = u32;
+
+	const MAX_LEN: usize = 64; // TODO configurage
+
+	/// The name this worker counts its fetch errors under, see `ocw_http::record_error`.
+	pub const WORKER: &[u8] = b"ocw-signed";
+
+	/// The prefix `decl_storage!` stored `Prices` under before the port to `#[pallet]`.
+	const DECL_STORAGE_PREFIX: &[u8] = b"OcwSignedModule";
+
+	/// A submitted price and the block it was submitted in.
+	#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub struct PricePoint<BlockNumber> {
+		pub price: u32,
+		pub block_number: BlockNumber,
 	}
-}
 
-// The pallet's events
-decl_event!(
-	pub enum Event<T> where AccountId = <T as system::Trait>::AccountId {
-		NewPrice(u32, AccountId),
+	// ref: https://serde.rs/container-attrs.html#crate
+	#[derive(Deserialize, Encode, Decode, Default)]
+	struct PriceInfo {
+		#[serde(rename(deserialize = "USD"), deserialize_with = "ocw_http::de_float_to_integer")]
+		usd: u32,
 	}
-);
 
-// The pallet's errors
-decl_error! {
-	pub enum Error for Module<T: Trait> {
+	/// The pallet's configuration trait.
+	#[pallet::config]
+	pub trait Config: frame_system::Config + CreateSignedTransaction<Call<Self>> {
+		/// The identifier type for an offchain worker.
+		type AuthorityId: AppCrypto<Self::Public, Self::Signature>;
+
+		/// The overarching event type.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// The overarching dispatch call type.
+		type Call: From<Call<Self>>;
 	}
-}
 
-// The pallet's dispatchable functions.
-decl_module! {
-	/// The module declaration.
-	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
-		// Initializing errors
-		// this includes information about your errors in the node's metadata.
-		// it is needed only if you are using errors in your pallet
-		type Error = Error<T>;
-
-		// Initializing events
-		// this is needed only if you are using events in your pallet
-		fn deposit_event() = default;
-
-		#[weight = 0]
-		pub fn submit_price_signed(origin, price: u32) -> DispatchResult {
-			let who = ensure_signed(origin)?;
-			Self::add_price(who, price);
-			
-			Ok(())
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// The last `MAX_LEN` submitted prices, a ring buffer starting at `PricesHead`.
+	///
+	/// Use `prices_in_order` to read them oldest first.
+	#[pallet::storage]
+	#[pallet::getter(fn prices)]
+	pub type Prices<T: Config> = StorageValue<_, Vec<PricePoint<T::BlockNumber>>, ValueQuery>;
+
+	/// The index of the oldest price in `Prices`, the next one overwritten once it is full.
+	#[pallet::storage]
+	pub type PricesHead<T> = StorageValue<_, u32, ValueQuery>;
+
+	#[pallet::event]
+	#[pallet::metadata(T::AccountId = "AccountId")]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A price was submitted. \[price, who\]
+		NewPrice(u32, T::AccountId),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// No key of `KEY_TYPE` in the keystore to sign with.
+		NoLocalAccount,
+		/// The price could not be fetched.
+		PriceFetchFailed,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_runtime_upgrade() -> Weight {
+			// `Prices` holds `PricePoint`s from 3.1.0 on.
+			if Self::storage_version().map_or(false, |version| version >= PalletVersion::new(3, 1, 0)) {
+				return 0;
+			}
+			Self::migrate_prices_from_decl_storage()
+				.saturating_add(Self::migrate_prices_to_price_points())
 		}
-		
-		fn offchain_worker() {
-			debug::native::info!("Submit signed: Offchain working starts running");
+
+		fn offchain_worker(_block_number: T::BlockNumber) {
+			log::info!("Submit signed: Offchain working starts running");
 
 			let res = Self::fetch_price_and_send_signed();
 
 			if let Err(e) = res {
-				debug::error!("Submit signed: Error happends: {}", e);
+				log::error!("Submit signed: Error happends: {:?}", e);
 			}
 		}
 	}
-}
 
-impl<T: Trait> Module<T> {
-	fn add_price(who: T::AccountId, price: u32) {
-		debug::info!("Submit signed: Adding to the prices: {}", price);
-		Prices::mutate(|prices| {
-			if prices.len() < MAX_LEN {
-				prices.push(price);
-			} else {
-				prices[price as usize % MAX_LEN] = price;
-			}
-		});
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		#[pallet::weight(0)]
+		pub fn submit_price_signed(origin: OriginFor<T>, price: u32) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			Self::add_price(who, price);
 
-		Self::deposit_event(RawEvent::NewPrice(price, who));
+			Ok(().into())
+		}
 	}
 
-	fn fetch_price_and_send_signed() -> Result<(), &'static str> {
-		let signer = Signer::<T, T::AuthorityId>::all_accounts();
-		if !signer.can_sign() {
-			return Err(
-				"No local accounts available. Consider adding one via `author_insertKey` RPC."
-			)?
+	impl<T: Config> Pallet<T> {
+		fn add_price(who: T::AccountId, price: u32) {
+			log::info!("Submit signed: Adding to the prices: {}", price);
+			let point = PricePoint { price, block_number: <frame_system::Pallet<T>>::block_number() };
+			Prices::<T>::mutate(|prices| {
+				if prices.len() < MAX_LEN {
+					prices.push(point);
+				} else {
+					let head = PricesHead::<T>::get() as usize % MAX_LEN;
+					prices[head] = point;
+					PricesHead::<T>::put(((head + 1) % MAX_LEN) as u32);
+				}
+			});
+
+			Self::deposit_event(Event::NewPrice(price, who));
 		}
 
-		// Make an external HTTP request to fetch the current price.
-		// Note this call will block until response is received.
-		let price = Self::fetch_price().map_err(|_| "Submit signed: Failed to fetch price")?;
-
-		// Using `send_signed_transaction` associated type we create and submit a transaction
-		// representing the call, we've just created.
-		// Submit signed will return a vector of results for all accounts that were found in the
-		// local keystore with expected `KEY_TYPE`.
-		let results = signer.send_signed_transaction(
-			|_account| {
-				// Received price is wrapped into a call to `submit_price` public function of this pallet.
-				// This means that the transaction, when executed, will simply call that function passing
-				// `price` as an argument.
-				Call::submit_price_signed(price)
+		/// The prices in `Prices`, oldest first.
+		pub fn prices_in_order() -> Vec<PricePoint<T::BlockNumber>> {
+			let mut prices = Prices::<T>::get();
+			let head = PricesHead::<T>::get() as usize;
+			if head < prices.len() {
+				prices.rotate_left(head);
 			}
-		);
+			prices
+		}
 
-		for (acc, res) in &results {
-			match res {
-				Ok(()) => debug::info!("Submit signed: [{:?}] Submitted price of {} cents", acc.id, price),
-				Err(e) => debug::error!("Submit signed: [{:?}] Failed to submit transcation, {:?}", acc.id, e),
+		/// The mean of the prices in `Prices`, `None` while there are none.
+		pub fn average_price() -> Option<u32> {
+			let prices = Prices::<T>::get();
+			if prices.is_empty() {
+				return None;
 			}
+			let sum: u64 = prices.iter().map(|point| point.price as u64).sum();
+			Some((sum / prices.len() as u64) as u32)
 		}
 
-		Ok(())
-	}
+		/// The time-weighted average of the prices in `Prices`, `None` while there are none.
+		///
+		/// Every price is weighted by the number of blocks it stayed the latest one, up to the
+		/// current block for the latest price. Falls back to `average_price` while all prices
+		/// were submitted in the current block.
+		pub fn twap() -> Option<u32> {
+			let prices = Self::prices_in_order();
+			let now = <frame_system::Pallet<T>>::block_number();
+			let mut weighted: u128 = 0;
+			let mut total: u128 = 0;
+			for (i, point) in prices.iter().enumerate() {
+				let until = prices.get(i + 1).map_or(now, |next| next.block_number);
+				let blocks: u128 = until.saturating_sub(point.block_number).unique_saturated_into();
+				weighted = weighted.saturating_add((point.price as u128).saturating_mul(blocks));
+				total = total.saturating_add(blocks);
+			}
+			if total.is_zero() {
+				return Self::average_price();
+			}
+			Some((weighted / total) as u32)
+		}
 
-	fn fetch_price() -> Result<u32, http::Error> {
-		let deadline = sp_io::offchain::timestamp().add(Duration::from_millis(5000));
-		// Initiate an external HTTP GET request.
-		// This is using high-level wrappers from `sp_runtime`, for the low-level calls that
-		// you can find in `sp_io`. The API is trying to be similar to `reqwest`, but
-		// since we are running in a custom WASM execution environment we can't simply
-		// import the library here.
-		let request = http::Request::get(
-			"https://min-api.cryptocompare.com/data/price?fsym=BTC&tsyms=USD"
-		);
-		// We set the deadline for sending of the request, note that awaiting response can
-		// have a separate deadline. Next we send the request, before that it's also possible
-		// to alter request headers or stream body content in case of non-GET requests.
-		let pending = request
-			.deadline(deadline)
-			.send()
-			.map_err(|_| http::Error::IoError)?;
-		// The request is already being processed by the host, we are free to do anything
-		// else in the worker (we can send multiple concurrent requests too).
-		// At some point however we probably want to check the response though,
-		// so we can block current thread and wait for it to finish.
-		// Note that since the request is being driven by the host, we don't have to wait
-		// for the request to have it complete, we will just not read the response.
-		let response = pending.try_wait(deadline)
-			.map_err(|_| http::Error::DeadlineReached)??;
-
-		if response.code != 200 {
-			debug::warn!("Submit signed: Unexpected status code: {}", response.code);
-			return Err(http::Error::Unknown);
+		/// Move `Prices` from the `decl_storage!` prefix to the prefix of the pallet's name in the
+		/// runtime, still as plain prices. Nothing is left to move on later upgrades.
+		pub(crate) fn migrate_prices_from_decl_storage() -> Weight {
+			let prefix = <T as frame_system::Config>::PalletInfo::name::<Self>().unwrap_or_default();
+			if prefix.as_bytes() == DECL_STORAGE_PREFIX {
+				return 0;
+			}
+			match frame_support::storage::migration::take_storage_value::<Vec<u32>>(
+				DECL_STORAGE_PREFIX,
+				b"Prices",
+				&[],
+			) {
+				Some(prices) => {
+					frame_support::storage::migration::put_storage_value(
+						prefix.as_bytes(),
+						b"Prices",
+						&[],
+						prices,
+					);
+					T::DbWeight::get().reads_writes(1, 2)
+				}
+				None => T::DbWeight::get().reads(1),
+			}
 		}
 
-		let body = response.body().collect::<Vec<u8>>();
+		/// Stamp the plain prices of before 3.1.0 with the current block, keeping their order.
+		pub(crate) fn migrate_prices_to_price_points() -> Weight {
+			let block_number = <frame_system::Pallet<T>>::block_number();
+			let translated = Prices::<T>::translate::<Vec<u32>, _>(|prices| {
+				prices.map(|prices| {
+					prices.into_iter().map(|price| PricePoint { price, block_number }).collect()
+				})
+			});
+			if translated.is_err() {
+				log::error!("Submit signed: Prices could not be migrated and were cleared");
+				Prices::<T>::kill();
+			}
+			PricesHead::<T>::kill();
+			T::DbWeight::get().reads_writes(2, 2)
+		}
 
-		let body_str = sp_std::str::from_utf8(&body).map_err(|_| {
-			debug::warn!("Not UTF8 body");
-			http::Error::Unknown
-		})?;
+		fn fetch_price_and_send_signed() -> Result<(), Error<T>> {
+			let signer = Signer::<T, T::AuthorityId>::all_accounts();
+			if !signer.can_sign() {
+				log::error!("No local accounts available. Consider adding one via `author_insertKey` RPC.");
+				return Err(Error::<T>::NoLocalAccount);
+			}
 
-		let price_info: PriceInfo = serde_json::from_str(&body_str).unwrap();
-		debug::warn!("Submit Signed: Got price: {} cents", price_info.usd);
+			// Make an external HTTP request to fetch the current price.
+			// Note this call will block until response is received.
+			let price = Self::fetch_price().map_err(|e| {
+				log::error!("Submit signed: Failed to fetch price: {:?}", e);
+				ocw_http::record_error::<OffchainHost>(WORKER, &e);
+				Error::<T>::PriceFetchFailed
+			})?;
+
+			// Using `send_signed_transaction` associated type we create and submit a transaction
+			// representing the call, we've just created.
+			// Submit signed will return a vector of results for all accounts that were found in the
+			// local keystore with expected `KEY_TYPE`.
+			let results = signer.send_signed_transaction(
+				|_account| {
+					// Received price is wrapped into a call to `submit_price` public function of this pallet.
+					// This means that the transaction, when executed, will simply call that function passing
+					// `price` as an argument.
+					Call::submit_price_signed(price)
+				}
+			);
+
+			for (acc, res) in &results {
+				match res {
+					Ok(()) => log::info!("Submit signed: [{:?}] Submitted price of {} cents", acc.id, price),
+					Err(e) => log::error!("Submit signed: [{:?}] Failed to submit transcation, {:?}", acc.id, e),
+				}
+			}
 
-		Ok(price_info.usd)
-	}
+			Ok(())
+		}
 
+		fn fetch_price() -> Result<u32, FetchError> {
+			// The host sends the request and waits at most 5s for the answer, which is decoded
+			// straight into `PriceInfo`. A status other than 200, or a body that is not UTF-8 or not
+			// the expected JSON, is a `FetchError`.
+			let price_info: PriceInfo =
+				Request::get("https://min-api.cryptocompare.com/data/price?fsym=BTC&tsyms=USD")
+					.timeout(5_000)
+					.fetch_json::<OffchainHost, _>()?;
+			log::warn!("Submit signed: Got price: {} cents", price_info.usd);
+
+			Ok(price_info.usd)
+		}
+	}
 }
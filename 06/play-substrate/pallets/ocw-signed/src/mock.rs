@@ -1,55 +1,101 @@
-// // Creating mock runtime here
-
-// use crate::{Module, Trait};
-// use sp_core::H256;
-// use frame_support::{impl_outer_origin, parameter_types, weights::Weight};
-// use sp_runtime::{
-// 	traits::{BlakeTwo256, IdentityLookup}, testing::Header, Perbill,
-// };
-
-// impl_outer_origin! {
-// 	pub enum Origin for Test {}
-// }
-
-// // For testing the pallet, we construct most of a mock runtime. This means
-// // first constructing a configuration type (`Test`) which `impl`s each of the
-// // configuration traits of pallets we want to use.
-// #[derive(Clone, Eq, PartialEq)]
-// pub struct Test;
-// parameter_types! {
-// 	pub const BlockHashCount: u64 = 250;
-// 	pub const MaximumBlockWeight: Weight = 1024;
-// 	pub const MaximumBlockLength: u32 = 2 * 1024;
-// 	pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
-// }
-// impl frame_system::Trait for Test {
-// 	type Origin = Origin;
-// 	type Call = ();
-// 	type Index = u64;
-// 	type BlockNumber = u64;
-// 	type Hash = H256;
-// 	type Hashing = BlakeTwo256;
-// 	type AccountId = u64;
-// 	type Lookup = IdentityLookup<Self::AccountId>;
-// 	type Header = Header;
-// 	type Event = ();
-// 	type BlockHashCount = BlockHashCount;
-// 	type MaximumBlockWeight = MaximumBlockWeight;
-// 	type MaximumBlockLength = MaximumBlockLength;
-// 	type AvailableBlockRatio = AvailableBlockRatio;
-// 	type Version = ();
-// 	type ModuleToIndex = ();
-// 	type AccountData = ();
-// 	type OnNewAccount = ();
-// 	type OnKilledAccount = ();
-// }
-// impl Trait for Test {
-// 	type Event = ();
-// }
-// pub type TemplateModule = Module<Test>;
-
-// // This function basically just builds a genesis storage key/value store according to
-// // our desired mockup.
-// pub fn new_test_ext() -> sp_io::TestExternalities {
-// 	frame_system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
-// }
+use crate as pallet_ocw_signed;
+use frame_support::parameter_types;
+use sp_core::sr25519::Signature;
+use sp_runtime::{
+	testing::{Header, TestXt},
+	traits::{BlakeTwo256, Extrinsic as ExtrinsicT, IdentifyAccount, IdentityLookup, Verify},
+};
+use frame_system as system;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+// The pallet is named differently from its old `decl_storage!` prefix, so `Prices` has to be
+// migrated.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		OcwSigned: pallet_ocw_signed::{Module, Call, Storage, Event<T>},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const SS58Prefix: u8 = 42;
+}
+
+pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::AccountId;
+
+impl system::Config for Test {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = sp_core::H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = SS58Prefix;
+}
+
+pub type Extrinsic = TestXt<Call, ()>;
+
+impl frame_system::offchain::SigningTypes for Test {
+	type Public = <Signature as Verify>::Signer;
+	type Signature = Signature;
+}
+
+impl<LocalCall> frame_system::offchain::SendTransactionTypes<LocalCall> for Test
+where
+	Call: From<LocalCall>,
+{
+	type OverarchingCall = Call;
+	type Extrinsic = Extrinsic;
+}
+
+impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Test
+where
+	Call: From<LocalCall>,
+{
+	fn create_transaction<C: frame_system::offchain::AppCrypto<Self::Public, Self::Signature>>(
+		call: Call,
+		_public: <Signature as Verify>::Signer,
+		_account: AccountId,
+		nonce: u64,
+	) -> Option<(Call, <Extrinsic as ExtrinsicT>::SignaturePayload)> {
+		Some((call, (nonce, ())))
+	}
+}
+
+impl pallet_ocw_signed::Config for Test {
+	type AuthorityId = pallet_ocw_signed::sr25519::AuthId;
+	type Event = Event;
+	type Call = Call;
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut ext: sp_io::TestExternalities =
+		system::GenesisConfig::default().build_storage::<Test>().unwrap().into();
+	// Events are only deposited from block 1 on.
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
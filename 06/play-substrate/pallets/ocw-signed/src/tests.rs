@@ -1,5 +1,211 @@
-// // Tests to be written here
+use crate::{mock::*, PricePoint, Prices};
+use codec::Decode;
+use frame_support::{
+	assert_ok,
+	storage::{migration, unhashed},
+	traits::{GetPalletVersion, OffchainWorker, OnRuntimeUpgrade, PalletVersion},
+};
+use sp_core::offchain::{
+	testing::{self, TestOffchainExt, TestTransactionPoolExt},
+	OffchainExt, TransactionPoolExt,
+};
+use sp_keystore::{testing::KeyStore, KeystoreExt, SyncCryptoStore};
+use std::sync::Arc;
 
-// use crate::{Error, mock::*};
-// use frame_support::{assert_ok, assert_noop};
+const PRICE_URL: &str = "https://min-api.cryptocompare.com/data/price?fsym=BTC&tsyms=USD";
 
+fn account(seed: u8) -> AccountId {
+	AccountId::from_raw([seed; 32])
+}
+
+fn submit_price_at(block_number: u64, price: u32) {
+	System::set_block_number(block_number);
+	assert_ok!(OcwSigned::submit_price_signed(Origin::signed(account(1)), price));
+}
+
+fn register_keystore(ext: &mut sp_io::TestExternalities) {
+	let keystore = KeyStore::new();
+	SyncCryptoStore::sr25519_generate_new(&keystore, crate::KEY_TYPE, None).unwrap();
+	ext.register_extension(KeystoreExt(Arc::new(keystore)));
+}
+
+fn prices_in_order() -> Vec<u32> {
+	OcwSigned::prices_in_order().into_iter().map(|point| point.price).collect()
+}
+
+#[test]
+fn submit_price_signed_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(OcwSigned::submit_price_signed(Origin::signed(account(1)), 155));
+
+		assert_eq!(OcwSigned::prices(), vec![PricePoint { price: 155, block_number: 1 }]);
+		let event: Event = crate::Event::NewPrice(155, account(1)).into();
+		assert_eq!(System::events().last().map(|record| &record.event), Some(&event));
+	})
+}
+
+#[test]
+fn oldest_prices_are_overwritten_when_full() {
+	new_test_ext().execute_with(|| {
+		for price in 0..64 {
+			submit_price_at(1, price);
+		}
+		submit_price_at(2, 1000);
+		submit_price_at(2, 1001);
+
+		assert_eq!(OcwSigned::prices().len(), 64);
+		let mut expected: Vec<u32> = (2..64).collect();
+		expected.extend(&[1000, 1001]);
+		assert_eq!(prices_in_order(), expected);
+	})
+}
+
+#[test]
+fn average_price_is_the_mean() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(OcwSigned::average_price(), None);
+
+		submit_price_at(1, 100);
+		submit_price_at(2, 200);
+		submit_price_at(3, 600);
+
+		assert_eq!(OcwSigned::average_price(), Some(300));
+	})
+}
+
+#[test]
+fn twap_weights_prices_by_blocks_held() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(OcwSigned::twap(), None);
+
+		submit_price_at(1, 100);
+		submit_price_at(4, 400);
+		System::set_block_number(5);
+
+		// 100 for three blocks and 400 for one.
+		assert_eq!(OcwSigned::twap(), Some(175));
+	})
+}
+
+#[test]
+fn twap_falls_back_to_the_mean_within_a_block() {
+	new_test_ext().execute_with(|| {
+		submit_price_at(1, 100);
+		submit_price_at(1, 200);
+
+		assert_eq!(OcwSigned::twap(), Some(150));
+	})
+}
+
+#[test]
+fn prices_are_migrated_from_decl_storage() {
+	new_test_ext().execute_with(|| {
+		let version_key =
+			PalletVersion::storage_key::<<Test as frame_system::Config>::PalletInfo, OcwSigned>().unwrap();
+		unhashed::kill(&version_key);
+		migration::put_storage_value(b"OcwSignedModule", b"Prices", &[], vec![1u32, 2, 3]);
+		System::set_block_number(7);
+
+		OcwSigned::on_runtime_upgrade();
+
+		assert_eq!(
+			Prices::<Test>::get(),
+			vec![
+				PricePoint { price: 1, block_number: 7 },
+				PricePoint { price: 2, block_number: 7 },
+				PricePoint { price: 3, block_number: 7 },
+			],
+		);
+		assert_eq!(migration::get_storage_value::<Vec<u32>>(b"OcwSignedModule", b"Prices", &[]), None);
+		assert_eq!(OcwSigned::storage_version(), Some(PalletVersion::new(3, 1, 0)));
+	})
+}
+
+#[test]
+fn prices_are_not_migrated_from_version_3_1_0() {
+	new_test_ext().execute_with(|| {
+		let version_key =
+			PalletVersion::storage_key::<<Test as frame_system::Config>::PalletInfo, OcwSigned>().unwrap();
+		unhashed::put(&version_key, &PalletVersion::new(3, 1, 0));
+		migration::put_storage_value(b"OcwSignedModule", b"Prices", &[], vec![1u32, 2, 3]);
+
+		OcwSigned::on_runtime_upgrade();
+
+		assert_eq!(OcwSigned::prices(), vec![]);
+		assert_eq!(
+			migration::get_storage_value::<Vec<u32>>(b"OcwSignedModule", b"Prices", &[]),
+			Some(vec![1, 2, 3]),
+		);
+	})
+}
+
+#[test]
+fn prices_are_not_migrated_again() {
+	new_test_ext().execute_with(|| {
+		submit_price_at(1, 100);
+
+		OcwSigned::on_runtime_upgrade();
+
+		assert_eq!(OcwSigned::prices(), vec![PricePoint { price: 100, block_number: 1 }]);
+	})
+}
+
+#[test]
+fn offchain_worker_submits_signed_price() {
+	let (offchain, state) = TestOffchainExt::new();
+	let (pool, pool_state) = TestTransactionPoolExt::new();
+	let mut ext = new_test_ext();
+	ext.register_extension(OffchainExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+	register_keystore(&mut ext);
+
+	state.write().expect_request(testing::PendingRequest {
+		method: "GET".into(),
+		uri: PRICE_URL.into(),
+		response: Some(br#"{"USD": 155.23}"#.to_vec()),
+		sent: true,
+		..Default::default()
+	});
+
+	ext.execute_with(|| {
+		OcwSigned::offchain_worker(1);
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert_eq!(tx.signature.unwrap().0, 0);
+		assert_eq!(tx.call, Call::from(crate::Call::submit_price_signed(155)));
+	})
+}
+
+#[test]
+fn malformed_price_response_is_counted_not_submitted() {
+	let (offchain, state) = TestOffchainExt::new();
+	let (pool, pool_state) = TestTransactionPoolExt::new();
+
+	let mut ext = new_test_ext();
+	ext.register_extension(OffchainExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+	register_keystore(&mut ext);
+
+	for response in &[&b"not json"[..], &[0xff, 0xfe][..]] {
+		state.write().expect_request(testing::PendingRequest {
+			method: "GET".into(),
+			uri: PRICE_URL.into(),
+			response: Some(response.to_vec()),
+			sent: true,
+			..Default::default()
+		});
+	}
+
+	ext.execute_with(|| {
+		OcwSigned::offchain_worker(1);
+		OcwSigned::offchain_worker(2);
+
+		assert!(pool_state.read().transactions.is_empty());
+		let counts = ocw_http::error_counts::<crate::OffchainHost>(crate::WORKER);
+		assert_eq!(counts.json, 1);
+		assert_eq!(counts.utf8, 1);
+		assert_eq!(counts.total(), 2);
+	})
+}
@@ -2,40 +2,12 @@
 
 /// A module for offchain worker send unsigned transaction
 
-use frame_support::{
-	debug,
-	decl_module,
-	decl_storage,
-	decl_event,
-	decl_error,
-	dispatch::{DispatchResult},
-};
-use frame_system::{
-	self as system,
-	ensure_none,
-	offchain::{
-		SubmitTransaction,
-		SendTransactionTypes,
-	},
-};
-use sp_std::vec::Vec;
-use sp_runtime::{
-		offchain::{
-			http,
-			Duration,
-		},
-		transaction_validity::{
-			InvalidTransaction,
-			ValidTransaction,
-			TransactionValidity,
-			TransactionSource,
-		},
-};
-use codec::{Encode, Decode};
-use sp_std::prelude::*;
-// We use `alt_serde`, and Xanewok-modified `serde_json` so that we can compile the program
-//   with serde(features `std`) and alt_serde(features `no_std`).
-use alt_serde::{Deserialize, Deserializer};
+pub use pallet::*;
+
+ocw_http::impl_host! {
+	/// The offchain host functions the worker fetches prices with.
+	pub struct OffchainHost;
+}
 
 #[cfg(test)]
 mod mock;
@@ -43,182 +15,291 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
-// TODO
-// type TokenPrice = u32;
-
-const MAX_LEN: usize = 64; // TODO configurage
+#[frame_support::pallet]
+pub mod pallet {
+	use codec::{Decode, Encode};
+	use frame_support::{
+		dispatch::DispatchResultWithPostInfo,
+		pallet_prelude::*,
+		traits::{GetPalletVersion, PalletInfo, PalletVersion},
+	};
+	use frame_system::{
+		offchain::{SendTransactionTypes, SubmitTransaction},
+		pallet_prelude::*,
+	};
+	use crate::OffchainHost;
+	use ocw_http::{FetchError, Request};
+	use serde::Deserialize;
+	use sp_runtime::{
+		traits::Member,
+		RuntimeAppPublic, RuntimeDebug,
+	};
+	use sp_std::prelude::*;
+
+	// TODO
+	// type TokenPrice = u32;
+
+	const MAX_LEN: usize = 64; // TODO configurage
+
+	/// The name this worker counts its fetch errors under, see `ocw_http::record_error`.
+	pub const WORKER: &[u8] = b"ocw-unsigned";
+
+	/// `InvalidTransaction::Custom` code of a price signed by a key outside the authority set.
+	pub const NOT_AN_AUTHORITY: u8 = 1;
+
+	/// The prefix `decl_storage!` stored `Prices` under before the port to `#[pallet]`.
+	const DECL_STORAGE_PREFIX: &[u8] = b"OcwUnsignedModule";
+
+	/// A price fetched by an authority's offchain worker at `block_number`, signed with the
+	/// authority's key `public`.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+	pub struct PricePayload<Public, BlockNumber> {
+		pub price: u32,
+		pub block_number: BlockNumber,
+		pub public: Public,
+	}
 
-// Specifying serde path as `alt_serde`
-// ref: https://serde.rs/container-attrs.html#crate
-#[serde(crate = "alt_serde")]
-#[derive(Deserialize, Encode, Decode, Default)]
-struct PriceInfo {
-	#[serde(rename(deserialize = "USD"), deserialize_with = "de_float_to_integer")]
-    usd: u32,
-}
+	// ref: https://serde.rs/container-attrs.html#crate
+	#[derive(Deserialize, Encode, Decode, Default)]
+	struct PriceInfo {
+		#[serde(rename(deserialize = "USD"), deserialize_with = "ocw_http::de_float_to_integer")]
+		usd: u32,
+	}
 
-pub fn de_float_to_integer<'de, D>(de: D) -> Result<u32, D::Error>
-where D: Deserializer<'de> {
-	let f: f32 = Deserialize::deserialize(de)?;
-	Ok(f as u32)
-}
+	/// The pallet's configuration trait.
+	#[pallet::config]
+	pub trait Config: frame_system::Config + SendTransactionTypes<Call<Self>> {
+		/// The overarching event type.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
 
-/// The pallet's configuration trait.
-pub trait Trait: system::Trait + SendTransactionTypes<Call<Self>> {
-	/// The overarching event type.
-	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+		/// The overarching dispatch call type.
+		type Call: From<Call<Self>>;
 
-	/// The overarching dispatch call type.
-	type Call: From<Call<Self>>;
-	
-}
+		/// The key authorities sign their prices with, e.g. their Aura key.
+		type AuthorityId: Member + Parameter + RuntimeAppPublic + Default + Ord;
 
-// This pallet's storage items.
-decl_storage! {
-	trait Store for Module<T: Trait> as OcwUnsignedModule {
-		/// A vector of recently submitted prices.
-		/// 
-		/// This is used to calculate average price, should have bounded size.
-		Prices get(fn prices): Vec<u32>;
+		/// The current authority set, whose members may submit signed prices.
+		type Authorities: Get<Vec<Self::AuthorityId>>;
 	}
-}
 
-// The pallet's events
-decl_event!(
-	pub enum Event<T> where AccountId = <T as system::Trait>::AccountId {
-		NewPrice(u32, AccountId),
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// A vector of recently submitted prices.
+	///
+	/// This is used to calculate average price, should have bounded size.
+	#[pallet::storage]
+	#[pallet::getter(fn prices)]
+	pub type Prices<T> = StorageValue<_, Vec<u32>, ValueQuery>;
+
+	/// The block of the latest signed price of each authority, so none is submitted twice.
+	#[pallet::storage]
+	pub type LastAuthorityPrice<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AuthorityId, T::BlockNumber>;
+
+	#[pallet::event]
+	#[pallet::metadata(T::AccountId = "AccountId", T::AuthorityId = "AuthorityId")]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A price was submitted. \[price, who\]
+		NewPrice(u32, T::AccountId),
+		/// A price signed by an authority was submitted. \[price, authority\]
+		NewAuthorityPrice(u32, T::AuthorityId),
 	}
-);
 
-// The pallet's errors
-decl_error! {
-	pub enum Error for Module<T: Trait> {
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The price response could not be parsed.
 		ParseError,
+		/// The price could not be fetched.
+		PriceFetchFailed,
+		/// The transaction pool refused the unsigned transaction.
+		SubmitFailed,
+		/// A key of the authority set could not sign the price.
+		SigningFailed,
+		/// The keystore holds no key of the authority set to sign the price with.
+		NoLocalAuthority,
 	}
-}
-
-// The pallet's dispatchable functions.
-decl_module! {
-	/// The module declaration.
-	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
-		// Initializing errors
-		// this includes information about your errors in the node's metadata.
-		// it is needed only if you are using errors in your pallet
-		type Error = Error<T>;
-
-		// Initializing events
-		// this is needed only if you are using events in your pallet
-		fn deposit_event() = default;
-
-		#[weight = 0]
-		pub fn submit_price_unsigned(origin, price: u32) -> DispatchResult {
-			// This ensures that the function can only be called via unsigned transaction.
-			ensure_none(origin)?;
-
-			// Add the price to the onchain storage, but mark it as coming from an empty address.
-			Self::add_price(Default::default(), price);
 
-			Ok(())
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_runtime_upgrade() -> Weight {
+			// `Prices` is stored under the pallet's name from 3.1.0 on.
+			if Self::storage_version().map_or(false, |version| version >= PalletVersion::new(3, 1, 0)) {
+				return 0;
+			}
+			Self::migrate_prices_from_decl_storage()
 		}
-		
-		fn offchain_worker() {
-			debug::native::info!("Offchain working starts running");
 
-			let res = Self::fetch_price_and_send_unsigned();
+		fn offchain_worker(block_number: T::BlockNumber) {
+			log::info!("Offchain working starts running");
+
+			let res = Self::fetch_price_and_send_unsigned(block_number);
 
 			if let Err(e) = res {
-				debug::error!("Error happends: {}", e);
+				log::error!("Error happends: {:?}", e);
 			}
 		}
 	}
-}
 
-impl<T: Trait> Module<T> {
-	fn add_price(who: T::AccountId, price: u32) {
-		debug::info!("Adding to the prices: {}", price);
-		Prices::mutate(|prices| {
-			if prices.len() < MAX_LEN {
-				prices.push(price);
-			} else {
-				prices[price as usize % MAX_LEN] = price;
-			}
-		});
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Not accepted from the transaction pool, see `validate_unsigned`: unsigned prices must
+		/// be signed by an authority and sent with `submit_price_unsigned_with_signed_payload`.
+		#[pallet::weight(0)]
+		pub fn submit_price_unsigned(origin: OriginFor<T>, price: u32) -> DispatchResultWithPostInfo {
+			// This ensures that the function can only be called via unsigned transaction.
+			ensure_none(origin)?;
 
-		Self::deposit_event(RawEvent::NewPrice(price, who));
-	}
+			// Add the price to the onchain storage, but mark it as coming from an empty address.
+			Self::add_price(Default::default(), price);
 
-	fn fetch_price_and_send_unsigned() -> Result<(), &'static str> {
+			Ok(().into())
+		}
 
-		let price = Self::fetch_price().map_err(|_| "Failed to fetch price")?;
+		/// Submit a price signed by a member of the authority set, see `validate_unsigned`.
+		#[pallet::weight(0)]
+		pub fn submit_price_unsigned_with_signed_payload(
+			origin: OriginFor<T>,
+			payload: PricePayload<T::AuthorityId, T::BlockNumber>,
+			_signature: <T::AuthorityId as RuntimeAppPublic>::Signature,
+		) -> DispatchResultWithPostInfo {
+			// The signature and the authority were checked in `validate_unsigned`.
+			ensure_none(origin)?;
 
-		let call = Call::submit_price_unsigned(price);
+			LastAuthorityPrice::<T>::insert(&payload.public, payload.block_number);
+			Self::add_price(Default::default(), payload.price);
+			Self::deposit_event(Event::NewAuthorityPrice(payload.price, payload.public));
 
-		SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into())
-			.map_err(|()| "Unable to submit unsigned transaction")?;
+			Ok(().into())
+		}
+	}
 
-		Ok(())
+	#[pallet::validate_unsigned]
+	impl<T: Config> ValidateUnsigned for Pallet<T> {
+		type Call = Call<T>;
+
+		fn validate_unsigned(
+			_source: TransactionSource,
+			call: &Self::Call
+		) -> TransactionValidity {
+			match call {
+				Call::submit_price_unsigned_with_signed_payload(payload, signature) =>
+					Self::validate_signed_payload(payload, signature),
+				_ => InvalidTransaction::Call.into()
+			}
+		}
 	}
 
-	fn fetch_price() -> Result<u32, http::Error> {
-		let deadline = sp_io::offchain::timestamp().add(Duration::from_millis(2000));
-		// Initiate an external HTTP GET request.
-		// This is using high-level wrappers from `sp_runtime`, for the low-level calls that
-		// you can find in `sp_io`. The API is trying to be similar to `reqwest`, but
-		// since we are running in a custom WASM execution environment we can't simply
-		// import the library here.
-		let request = http::Request::get(
-			"https://min-api.cryptocompare.com/data/price?fsym=BTC&tsyms=USD"
-		);
-		// We set the deadline for sending of the request, note that awaiting response can
-		// have a separate deadline. Next we send the request, before that it's also possible
-		// to alter request headers or stream body content in case of non-GET requests.
-		let pending = request
-			.deadline(deadline)
-			.send()
-			.map_err(|_| http::Error::IoError)?;
-		// The request is already being processed by the host, we are free to do anything
-		// else in the worker (we can send multiple concurrent requests too).
-		// At some point however we probably want to check the response though,
-		// so we can block current thread and wait for it to finish.
-		// Note that since the request is being driven by the host, we don't have to wait
-		// for the request to have it complete, we will just not read the response.
-		let response = pending.try_wait(deadline)
-			.map_err(|_| http::Error::DeadlineReached)??;
-
-		if response.code != 200 {
-			debug::warn!("Unexpected status code: {}", response.code);
-			return Err(http::Error::Unknown);
+	impl<T: Config> Pallet<T> {
+		fn add_price(who: T::AccountId, price: u32) {
+			log::info!("Adding to the prices: {}", price);
+			Prices::<T>::mutate(|prices| {
+				if prices.len() < MAX_LEN {
+					prices.push(price);
+				} else {
+					prices[price as usize % MAX_LEN] = price;
+				}
+			});
+
+			Self::deposit_event(Event::NewPrice(price, who));
+		}
+
+		/// Move `Prices` from the `decl_storage!` prefix to the prefix of the pallet's name in the
+		/// runtime. Nothing is left to move on later upgrades.
+		pub(crate) fn migrate_prices_from_decl_storage() -> Weight {
+			let prefix = <T as frame_system::Config>::PalletInfo::name::<Self>().unwrap_or_default();
+			if prefix.as_bytes() == DECL_STORAGE_PREFIX {
+				return 0;
+			}
+			match frame_support::storage::migration::take_storage_value::<Vec<u32>>(
+				DECL_STORAGE_PREFIX,
+				b"Prices",
+				&[],
+			) {
+				Some(prices) => {
+					Prices::<T>::put(prices);
+					T::DbWeight::get().reads_writes(1, 2)
+				}
+				None => T::DbWeight::get().reads(1),
+			}
 		}
 
-		let body = response.body().collect::<Vec<u8>>();
+		/// Accept a price signed by a member of the current authority set, at most one per
+		/// authority and block, and none older than the authority's latest.
+		fn validate_signed_payload(
+			payload: &PricePayload<T::AuthorityId, T::BlockNumber>,
+			signature: &<T::AuthorityId as RuntimeAppPublic>::Signature,
+		) -> TransactionValidity {
+			if !T::Authorities::get().contains(&payload.public) {
+				return InvalidTransaction::Custom(NOT_AN_AUTHORITY).into();
+			}
+			let signed = payload.using_encoded(|encoded| payload.public.verify(&encoded, signature));
+			if !signed {
+				return InvalidTransaction::BadProof.into();
+			}
+			if payload.block_number > <frame_system::Pallet<T>>::block_number() {
+				return InvalidTransaction::Future.into();
+			}
+			if LastAuthorityPrice::<T>::get(&payload.public)
+				.map_or(false, |last| payload.block_number <= last)
+			{
+				return InvalidTransaction::Stale.into();
+			}
 
-		let body_str = sp_std::str::from_utf8(&body).map_err(|_| {
-			debug::warn!("Not UTF8 body");
-			http::Error::Unknown
-		})?;
+			ValidTransaction::with_tag_prefix("OffchainWorkerSignedPayload")
+				.and_provides((&payload.public, payload.block_number))
+				.longevity(5)
+				.propagate(true)
+				.build()
+		}
 
-		let price_info: PriceInfo = serde_json::from_str(&body_str).unwrap();
+		/// Submit the price signed by every key of the authority set in the keystore.
+		pub(crate) fn fetch_price_and_send_unsigned(block_number: T::BlockNumber) -> Result<(), Error<T>> {
+			let price = Self::fetch_price().map_err(|e| {
+				log::error!("Failed to fetch price: {:?}", e);
+				ocw_http::record_error::<OffchainHost>(WORKER, &e);
+				match e {
+					FetchError::Json => Error::<T>::ParseError,
+					_ => Error::<T>::PriceFetchFailed,
+				}
+			})?;
+
+			let authorities = T::Authorities::get();
+			let keys: Vec<_> = T::AuthorityId::all()
+				.into_iter()
+				.filter(|key| authorities.contains(key))
+				.collect();
+			if keys.is_empty() {
+				return Err(Error::<T>::NoLocalAuthority);
+			}
 
-		debug::warn!("Got price: {} cents", price_info.usd);
+			for public in keys {
+				let payload = PricePayload { price, block_number, public };
+				let signature = payload
+					.using_encoded(|encoded| payload.public.sign(&encoded))
+					.ok_or(Error::<T>::SigningFailed)?;
+				let call = Call::submit_price_unsigned_with_signed_payload(payload, signature);
 
-		Ok(price_info.usd)
-	}
+				SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into())
+					.map_err(|()| Error::<T>::SubmitFailed)?;
+			}
 
-}
+			Ok(())
+		}
 
-impl<T: Trait> frame_support::unsigned::ValidateUnsigned for Module<T> {
-	type Call = Call<T>;
-
-	fn validate_unsigned(
-		_source: TransactionSource,
-		call: &Self::Call
-	) -> TransactionValidity {
-		match call {
-			Call::submit_price_unsigned(input) =>
-				ValidTransaction::with_tag_prefix("OffchainWorkerUnsignedTx")
-					.and_provides(input)
-					.build(),
-			_ => InvalidTransaction::Call.into()
+		fn fetch_price() -> Result<u32, FetchError> {
+			// The host sends the request and waits at most 2s for the answer, which is decoded
+			// straight into `PriceInfo`. A status other than 200, or a body that is not UTF-8 or not
+			// the expected JSON, is a `FetchError`.
+			let price_info: PriceInfo =
+				Request::get("https://min-api.cryptocompare.com/data/price?fsym=BTC&tsyms=USD")
+					.timeout(2_000)
+					.fetch_json::<OffchainHost, _>()?;
+			log::warn!("Got price: {} cents", price_info.usd);
+
+			Ok(price_info.usd)
 		}
 	}
-}
\ No newline at end of file
+}
@@ -1,5 +1,212 @@
-// Tests to be written here
+use crate::{mock::*, LastAuthorityPrice, PricePayload, Prices, NOT_AN_AUTHORITY};
+use codec::Decode;
+use frame_support::{
+	assert_noop, assert_ok,
+	storage::{migration, unhashed},
+	traits::{OffchainWorker, OnRuntimeUpgrade, PalletVersion},
+	unsigned::ValidateUnsigned,
+};
+use parking_lot::RwLock;
+use sp_core::offchain::{
+	testing::{self, OffchainState, PoolState, TestOffchainExt, TestTransactionPoolExt},
+	OffchainExt, TransactionPoolExt,
+};
+use sp_runtime::{
+	testing::{TestSignature, UintAuthorityId},
+	transaction_validity::{InvalidTransaction, TransactionSource, TransactionValidity},
+	DispatchError, RuntimeAppPublic,
+};
+use std::sync::Arc;
 
-use crate::{Error, mock::*};
-use frame_support::{assert_ok, assert_noop};
+const PRICE_URL: &str = "https://min-api.cryptocompare.com/data/price?fsym=BTC&tsyms=USD";
 
+fn new_offchain_test_ext() -> (sp_io::TestExternalities, Arc<RwLock<OffchainState>>, Arc<RwLock<PoolState>>) {
+	let (offchain, state) = TestOffchainExt::new();
+	let (pool, pool_state) = TestTransactionPoolExt::new();
+
+	let mut ext = new_test_ext();
+	ext.register_extension(OffchainExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+	(ext, state, pool_state)
+}
+
+fn expect_price_request(state: &RwLock<OffchainState>, response: &[u8]) {
+	state.write().expect_request(testing::PendingRequest {
+		method: "GET".into(),
+		uri: PRICE_URL.into(),
+		response: Some(response.to_vec()),
+		sent: true,
+		..Default::default()
+	});
+}
+
+fn signed_payload(authority: u64, block_number: u64, price: u32) -> (PricePayload<UintAuthorityId, u64>, TestSignature) {
+	let payload = PricePayload { price, block_number, public: UintAuthorityId(authority) };
+	let signature = payload.using_encoded(|encoded| payload.public.sign(&encoded)).unwrap();
+	(payload, signature)
+}
+
+fn validate_signed_payload(payload: PricePayload<UintAuthorityId, u64>, signature: TestSignature) -> TransactionValidity {
+	OcwUnsigned::validate_unsigned(
+		TransactionSource::External,
+		&crate::Call::submit_price_unsigned_with_signed_payload(payload, signature),
+	)
+}
+
+#[test]
+fn submit_price_unsigned_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(OcwUnsigned::submit_price_unsigned(Origin::none(), 155));
+
+		assert_eq!(OcwUnsigned::prices(), vec![155]);
+		let event: Event = crate::Event::NewPrice(155, 0).into();
+		assert_eq!(System::events().last().map(|record| &record.event), Some(&event));
+	})
+}
+
+#[test]
+fn submit_price_unsigned_rejects_signed_origin() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			OcwUnsigned::submit_price_unsigned(Origin::signed(1), 155),
+			DispatchError::BadOrigin
+		);
+	})
+}
+
+#[test]
+fn validate_unsigned_rejects_prices_without_a_signed_payload() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			OcwUnsigned::validate_unsigned(
+				TransactionSource::Local,
+				&crate::Call::submit_price_unsigned(155),
+			),
+			InvalidTransaction::Call.into(),
+		);
+	})
+}
+
+#[test]
+fn prices_are_migrated_from_decl_storage() {
+	new_test_ext().execute_with(|| {
+		let version_key =
+			PalletVersion::storage_key::<<Test as frame_system::Config>::PalletInfo, OcwUnsigned>().unwrap();
+		unhashed::kill(&version_key);
+		migration::put_storage_value(b"OcwUnsignedModule", b"Prices", &[], vec![1u32, 2, 3]);
+
+		OcwUnsigned::on_runtime_upgrade();
+
+		assert_eq!(Prices::<Test>::get(), vec![1, 2, 3]);
+		assert_eq!(migration::get_storage_value::<Vec<u32>>(b"OcwUnsignedModule", b"Prices", &[]), None);
+	})
+}
+
+#[test]
+fn offchain_worker_needs_an_authority_key() {
+	let (mut ext, state, pool_state) = new_offchain_test_ext();
+	expect_price_request(&state, br#"{"USD": 155.23}"#);
+	// Key 4 is not in the authority set.
+	UintAuthorityId::set_all_keys(vec![4u64]);
+
+	ext.execute_with(|| {
+		assert!(matches!(
+			OcwUnsigned::fetch_price_and_send_unsigned(1),
+			Err(crate::Error::NoLocalAuthority)
+		));
+		assert!(pool_state.read().transactions.is_empty());
+	})
+}
+
+#[test]
+fn malformed_price_response_is_counted_not_submitted() {
+	let (mut ext, state, pool_state) = new_offchain_test_ext();
+	expect_price_request(&state, b"not json");
+	expect_price_request(&state, &[0xff, 0xfe]);
+
+	ext.execute_with(|| {
+		OcwUnsigned::offchain_worker(1);
+		OcwUnsigned::offchain_worker(2);
+
+		assert!(pool_state.read().transactions.is_empty());
+		let counts = ocw_http::error_counts::<crate::OffchainHost>(crate::WORKER);
+		assert_eq!(counts.json, 1);
+		assert_eq!(counts.utf8, 1);
+		assert_eq!(counts.total(), 2);
+	})
+}
+
+#[test]
+fn offchain_worker_signs_price_with_authority_keys() {
+	let (mut ext, state, pool_state) = new_offchain_test_ext();
+	expect_price_request(&state, br#"{"USD": 155.23}"#);
+	// Key 4 is not in the authority set.
+	UintAuthorityId::set_all_keys(vec![2u64, 4]);
+
+	ext.execute_with(|| {
+		OcwUnsigned::offchain_worker(1);
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert_eq!(tx.signature, None);
+		let (payload, signature) = signed_payload(2, 1, 155);
+		assert_eq!(
+			tx.call,
+			Call::from(crate::Call::submit_price_unsigned_with_signed_payload(payload, signature)),
+		);
+	})
+}
+
+#[test]
+fn signed_payload_of_an_authority_is_valid() {
+	new_test_ext().execute_with(|| {
+		let (payload, signature) = signed_payload(1, 1, 155);
+		let valid = validate_signed_payload(payload, signature).unwrap();
+
+		let (payload, signature) = signed_payload(1, 1, 156);
+		let same_block = validate_signed_payload(payload, signature).unwrap();
+		let (payload, signature) = signed_payload(2, 1, 155);
+		let other_authority = validate_signed_payload(payload, signature).unwrap();
+
+		// One price per authority and block.
+		assert_eq!(valid.provides, same_block.provides);
+		assert_ne!(valid.provides, other_authority.provides);
+	})
+}
+
+#[test]
+fn signed_payload_is_checked() {
+	new_test_ext().execute_with(|| {
+		let (payload, signature) = signed_payload(4, 1, 155);
+		assert_eq!(
+			validate_signed_payload(payload, signature),
+			InvalidTransaction::Custom(NOT_AN_AUTHORITY).into(),
+		);
+
+		let (payload, _) = signed_payload(1, 1, 155);
+		let (_, signature) = signed_payload(1, 1, 156);
+		assert_eq!(validate_signed_payload(payload, signature), InvalidTransaction::BadProof.into());
+
+		let (payload, signature) = signed_payload(1, 2, 155);
+		assert_eq!(validate_signed_payload(payload, signature), InvalidTransaction::Future.into());
+	})
+}
+
+#[test]
+fn signed_payload_is_accepted_once() {
+	new_test_ext().execute_with(|| {
+		let (payload, signature) = signed_payload(1, 1, 155);
+		assert_ok!(OcwUnsigned::submit_price_unsigned_with_signed_payload(
+			Origin::none(),
+			payload.clone(),
+			signature.clone(),
+		));
+
+		assert_eq!(OcwUnsigned::prices(), vec![155]);
+		assert_eq!(LastAuthorityPrice::<Test>::get(UintAuthorityId(1)), Some(1));
+		let event: Event = crate::Event::NewAuthorityPrice(155, UintAuthorityId(1)).into();
+		assert_eq!(System::events().last().map(|record| &record.event), Some(&event));
+		assert_eq!(validate_signed_payload(payload, signature), InvalidTransaction::Stale.into());
+	})
+}